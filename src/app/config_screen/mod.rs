@@ -33,6 +33,14 @@ impl<G: Game, R: Renderer<G>> Renderer<G> for RendererWrapper<R> {
     fn debug_state(&self, game: &G, player_index: usize) -> G::DebugState {
         self.0.borrow().debug_state(game, player_index)
     }
+    fn debug_query(
+        &self,
+        game: &G,
+        player_index: usize,
+        query: G::DebugQuery,
+    ) -> G::DebugQueryResponse {
+        self.0.borrow().debug_query(game, player_index, query)
+    }
 }
 
 pub trait DeepConfig<T>: ui::Config<T> {
@@ -65,16 +73,27 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
     pub fn ui<'a>(&'a mut self) -> impl ui::Widget + 'a {
         if self.add_player_button.clicked() {
             if self.player_configs.len() < *self.player_count_range.end() {
+                let used_teams: HashSet<usize> = self
+                    .player_configs
+                    .iter()
+                    .map(|config| config.team())
+                    .collect();
+                let team = (0..).find(|team| !used_teams.contains(team)).unwrap();
                 self.player_configs.push(PlayerConfigWidget::new(
                     &self.theme,
                     &self.player_config_options,
                     0,
+                    team,
                 ));
             }
         }
         if self.remove_player_button.clicked() {
             if self.player_configs.len() > *self.player_count_range.start() {
                 self.player_configs.pop();
+                let team_count = self.player_configs.len();
+                for config in &mut self.player_configs {
+                    config.limit_team(team_count);
+                }
             }
         }
         #[cfg(not(target_arch = "wasm32"))]
@@ -120,6 +139,7 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
             )) as Box<dyn Widget>
         };
         let theme = &self.theme;
+        let team_count = self.player_configs.len();
         let players_section = ui::row(
             self.player_configs
                 .iter_mut()
@@ -134,9 +154,10 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
                                 Color::GRAY,
                             )
                             .center(),
+                            config.team_ui(team_count),
                             config.ui().center()
                         ]
-                        .fixed_size(vec2(200.0, 100.0))
+                        .fixed_size(vec2(200.0, 130.0))
                         .center()
                     }) as _
                 })
@@ -238,12 +259,23 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
                 .iter_mut()
                 .map(|config| config.create())
                 .collect();
-            return Some(geng::Transition::Push(Box::new(GameScreen::new(
-                self.theme.geng(),
-                GameProcessor::new(None, self.game_init_config().into(), players),
-                self.renderer.clone(),
-                self.preferences.clone(),
-            ))));
+            let teams = self.teams();
+            match GameProcessor::new_with_teams(
+                None,
+                self.game_init_config().into(),
+                players,
+                Some(teams),
+            ) {
+                Ok(processor) => {
+                    return Some(geng::Transition::Push(Box::new(GameScreen::new(
+                        self.theme.geng(),
+                        processor,
+                        self.renderer.clone(),
+                        self.preferences.clone(),
+                    ))));
+                }
+                Err(e) => error!("Failed to start game: {}", e),
+            }
         }
         #[cfg(not(target_arch = "wasm32"))]
         {
@@ -251,7 +283,10 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
                 if let Some(path) = select_file(translate("Select file to replay")) {
                     return Some(geng::Transition::Push(Box::new(GameScreen::replay(
                         self.theme.geng(),
-                        futures::executor::block_on(History::load(path.to_str().unwrap())),
+                        futures::executor::block_on(History::load(
+                            path.to_str().unwrap(),
+                            self.preferences.borrow().history_options(),
+                        )),
                         self.renderer.clone(),
                         self.preferences.clone(),
                     ))));
@@ -259,25 +294,43 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
             }
             if self.repeat_button.clicked() {
                 if let Some(path) = select_file(translate("Select file to repeat")) {
+                    let teams = self.teams();
                     let players: Vec<Box<dyn Player<G>>> = self
                         .player_configs
                         .iter_mut()
                         .map(|config| config.create())
                         .collect();
-                    return Some(geng::Transition::Push(Box::new(GameScreen::new(
-                        self.theme.geng(),
-                        GameProcessor::<G>::repeat(
-                            std::fs::File::open(path).expect("Failed to open game log file"),
-                            players,
-                        ),
-                        self.renderer.clone(),
-                        self.preferences.clone(),
-                    ))));
+                    match GameProcessor::<G>::repeat_with_teams(
+                        std::fs::File::open(path).expect("Failed to open game log file"),
+                        players,
+                        Some(teams),
+                    ) {
+                        Ok(processor) => {
+                            return Some(geng::Transition::Push(Box::new(GameScreen::new(
+                                self.theme.geng(),
+                                processor,
+                                self.renderer.clone(),
+                                self.preferences.clone(),
+                            ))));
+                        }
+                        Err(e) => error!("Failed to repeat game: {}", e),
+                    }
                 }
             }
         }
         None
     }
+    /// Team of every player, renumbered so that there are no empty teams
+    fn teams(&self) -> Vec<usize> {
+        let mut numbers = HashMap::new();
+        self.player_configs
+            .iter()
+            .map(|config| {
+                let next = numbers.len();
+                *numbers.entry(config.team()).or_insert(next)
+            })
+            .collect()
+    }
     fn game_init_config(&self) -> GameInitConfig<G> {
         #[cfg(not(target_arch = "wasm32"))]
         if let Some(path) = &self.game_state_path {
@@ -295,6 +348,10 @@ impl<G: Game, R: Renderer<G>> Data<G, R> {
                 .iter()
                 .map(|config| config.to_options())
                 .collect(),
+            teams: Some(self.teams()),
+            real_time: None,
+            post_mortem_replay: None,
+            profile: None,
         }
     }
 }
@@ -318,11 +375,12 @@ impl<G: Game, R: Renderer<G>> ConfigScreen<G, R> {
         let renderer = RendererWrapper(Rc::new(RefCell::new(renderer)));
         let player_config_options = Rc::new(player_config_options);
         let mut player_configs = Vec::with_capacity(player_config_defaults.len());
-        for default in player_config_defaults {
+        for (index, default) in player_config_defaults.into_iter().enumerate() {
             player_configs.push(PlayerConfigWidget::new(
                 theme,
                 &player_config_options,
                 default,
+                index,
             ));
         }
         Self {
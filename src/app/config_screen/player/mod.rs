@@ -20,6 +20,8 @@ pub(crate) struct PlayerConfigWidget<G: Game> {
     current_config: Box<dyn PlayerConfig<G>>,
     current_config_index: usize,
     button: ui::Button,
+    team: usize,
+    team_button: ui::Button,
 }
 
 impl<G: Game> PlayerConfigWidget<G> {
@@ -27,6 +29,7 @@ impl<G: Game> PlayerConfigWidget<G> {
         theme: &Rc<ui::Theme>,
         options: &Rc<Vec<Box<dyn Fn() -> Box<dyn PlayerConfig<G>>>>>,
         default_option: usize,
+        team: usize,
     ) -> Self {
         let current_config = options[default_option]();
         Self {
@@ -35,6 +38,8 @@ impl<G: Game> PlayerConfigWidget<G> {
             current_config,
             current_config_index: default_option,
             button: ui::Button::new(),
+            team,
+            team_button: ui::Button::new(),
         }
     }
     pub fn ui<'a>(&'a mut self) -> impl ui::Widget + 'a {
@@ -51,6 +56,25 @@ impl<G: Game> PlayerConfigWidget<G> {
             self.current_config.ui(),
         ]
     }
+    pub fn team_ui<'a>(&'a mut self, team_count: usize) -> impl ui::Widget + 'a {
+        use ui::*;
+        if self.team_button.clicked() {
+            self.team = (self.team + 1) % team_count.max(1);
+        }
+        ui::Button::text(
+            &mut self.team_button,
+            format!("{} {}", translate("Team"), self.team + 1),
+            &self.theme,
+        )
+        .center()
+    }
+    pub fn team(&self) -> usize {
+        self.team
+    }
+    /// Move to the last team if current one no longer exists
+    pub fn limit_team(&mut self, team_count: usize) {
+        self.team = self.team.min(team_count.max(1) - 1);
+    }
     pub fn ready(&mut self) -> bool {
         self.current_config.ready()
     }
@@ -2,16 +2,29 @@ use super::*;
 
 mod ui;
 
+struct DebugQueryRequest<G: Game> {
+    player_index: usize,
+    query: G::DebugQuery,
+    response_sender: std::sync::mpsc::Sender<G::DebugQueryResponse>,
+}
+
+/// Queries waiting to be answered by the renderer, `None` after the screen is closed
+type DebugQueries<G> = Arc<Mutex<Option<Vec<DebugQueryRequest<G>>>>>;
+
 pub struct GameScreen<G: Game, R: Renderer<G>> {
     geng: Rc<Geng>,
     processor: Option<BackgroundGameProcessor<G>>,
     debug_state: Arc<Mutex<Vec<G::DebugState>>>,
+    debug_queries: DebugQueries<G>,
     renderer: R,
     history: History<G, R::ExtraData>,
     current_tick: f64,
+    /// Index of the first breakpoint playback has not reached yet
+    next_breakpoint: usize,
     paused: Rc<Cell<bool>>,
     view_speed_modifier: Rc<Cell<f64>>,
     volume: Rc<Cell<f64>>,
+    debug_data_filter: Rc<RefCell<DebugDataFilter>>,
     ui: ui::UI,
     ui_controller: geng::ui::Controller,
     need_close: bool,
@@ -22,6 +35,16 @@ pub struct GameScreen<G: Game, R: Renderer<G>> {
 pub struct AppPreferences<T> {
     pub view_speed_modifier: f64,
     pub volume: f64,
+    #[serde(default)]
+    pub debug_data_limits: DebugDataLimits,
+    /// Bytes of history and debug data to keep in memory, the rest is moved to a temporary file
+    #[serde(default)]
+    pub history_memory_budget: Option<u64>,
+    #[serde(default)]
+    pub history_keyframes: HistoryKeyframes,
+    /// Number of recently visited ticks kept to speed up scrubbing the timeline
+    #[serde(default)]
+    pub history_snapshot_cache: usize,
     pub renderer: T,
 }
 
@@ -30,11 +53,27 @@ impl<T: Default> Default for AppPreferences<T> {
         Self {
             volume: 0.5,
             view_speed_modifier: 0.0,
+            debug_data_limits: default(),
+            history_memory_budget: None,
+            history_keyframes: default(),
+            history_snapshot_cache: 0,
             renderer: default(),
         }
     }
 }
 
+impl<T> AppPreferences<T> {
+    /// Settings for histories of games shown in the app
+    pub fn history_options(&self) -> HistoryOptions {
+        HistoryOptions {
+            keyframes: self.history_keyframes,
+            snapshot_cache_size: self.history_snapshot_cache,
+            memory_budget: self.history_memory_budget,
+            debug_data_limits: self.debug_data_limits.clone(),
+        }
+    }
+}
+
 impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
     fn new_impl(
         geng: &Rc<Geng>,
@@ -47,6 +86,7 @@ impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
         let paused = Rc::new(Cell::new(false));
         let view_speed_modifier = Rc::new(Cell::new(preferences.borrow().view_speed_modifier));
         let volume = Rc::new(Cell::new(preferences.borrow().volume));
+        let debug_data_filter = Rc::new(RefCell::new(DebugDataFilter::default()));
         let debug_state: Arc<Mutex<Vec<G::DebugState>>> = Arc::new(Mutex::new(
             (0..processor
                 .as_ref()
@@ -54,16 +94,37 @@ impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
                 .map(|index| renderer.debug_state(history.current_state().current.game, index))
                 .collect(),
         ));
+        let debug_queries: DebugQueries<G> = Arc::new(Mutex::new(Some(Vec::new())));
 
         let processor = processor.map(|processor| {
             BackgroundGameProcessor::new(
                 processor,
                 history.tick_handler(),
                 Some(DebugInterface {
-                    debug_command_handler: Box::new(history.debug_command_handler()),
-                    debug_state: Box::new({
+                    debug_command_handler: Arc::new(history.debug_command_handler()),
+                    debug_state: Arc::new({
                         let debug_state = debug_state.clone();
-                        move |player_index| debug_state.lock().unwrap()[player_index].clone()
+                        move |player_index| Some(debug_state.lock().unwrap()[player_index].clone())
+                    }),
+                    debug_query: Arc::new({
+                        let debug_queries = debug_queries.clone();
+                        move |player_index, query| {
+                            if cfg!(target_arch = "wasm32") {
+                                // Players run in the same thread, so the answer would never come
+                                return None;
+                            }
+                            let (response_sender, response_receiver) = std::sync::mpsc::channel();
+                            debug_queries
+                                .lock()
+                                .unwrap()
+                                .as_mut()?
+                                .push(DebugQueryRequest {
+                                    player_index,
+                                    query,
+                                    response_sender,
+                                });
+                            response_receiver.recv().ok()
+                        }
                     }),
                 }),
             )
@@ -71,14 +132,23 @@ impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
         Self {
             geng: geng.clone(),
             debug_state,
+            debug_queries,
             processor,
             renderer,
             history,
             current_tick: 0.0,
+            next_breakpoint: 0,
             paused: paused.clone(),
             view_speed_modifier: view_speed_modifier.clone(),
             volume: volume.clone(),
-            ui: ui::UI::new(geng, &paused, &view_speed_modifier, &volume),
+            debug_data_filter: debug_data_filter.clone(),
+            ui: ui::UI::new(
+                geng,
+                &paused,
+                &view_speed_modifier,
+                &volume,
+                &debug_data_filter,
+            ),
             ui_controller: geng::ui::Controller::new(),
             need_close: false,
             preferences,
@@ -90,7 +160,10 @@ impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
         renderer: R,
         preferences: Rc<RefCell<AutoSave<AppPreferences<R::Preferences>>>>,
     ) -> Self {
-        let history = History::new(processor.game().clone());
+        let history = History::new(
+            processor.game().clone(),
+            &preferences.borrow().history_options(),
+        );
         Self::new_impl(geng, history, Some(processor), renderer, preferences)
     }
     pub fn replay(
@@ -101,6 +174,17 @@ impl<G: Game, R: Renderer<G>> GameScreen<G, R> {
     ) -> Self {
         Self::new_impl(geng, history, None, renderer, preferences)
     }
+    fn ticks_per_second(&self) -> f64 {
+        // Real-time games are viewed at the speed they are actually played
+        match self
+            .processor
+            .as_ref()
+            .and_then(|processor| processor.real_time())
+        {
+            Some(real_time) => real_time.ticks_per_second,
+            None => self.renderer.default_tps(),
+        }
+    }
 }
 
 impl<G: Game, R: Renderer<G>> geng::State for GameScreen<G, R>
@@ -114,16 +198,42 @@ where
         if self.volume.get() != self.preferences.borrow().volume {
             self.preferences.borrow_mut().volume = self.volume.get();
         }
+        let ticks_per_second = self.ticks_per_second();
         let history_len = self.history.len();
         self.current_tick = self.current_tick.min(history_len as f64);
         let mut process_events = false;
+        let breakpoints = self.history.breakpoints();
         if let Some(time) = self.ui.timeline_change() {
             self.current_tick = time;
+            self.next_breakpoint =
+                breakpoints.partition_point(|breakpoint| breakpoint.tick as f64 <= time);
         } else {
             if !self.paused.get() {
-                self.current_tick += delta_time
-                    * ui::view_speed(self.view_speed_modifier.get(), self.renderer.default_tps());
+                self.current_tick +=
+                    delta_time * ui::view_speed(self.view_speed_modifier.get(), ticks_per_second);
                 process_events = true;
+                if let Some(breakpoint) = breakpoints.get(self.next_breakpoint) {
+                    let tick = breakpoint.tick;
+                    if tick as f64 <= self.current_tick {
+                        self.current_tick = tick as f64;
+                        self.paused.set(true);
+                        self.next_breakpoint =
+                            breakpoints.partition_point(|breakpoint| breakpoint.tick <= tick);
+                    }
+                }
+            }
+        }
+
+        // Answer queries before letting the processor proceed, as players asking them are waiting
+        if let Some(queries) = self.debug_queries.lock().unwrap().as_mut() {
+            for request in queries.drain(..) {
+                let response = self.renderer.debug_query(
+                    self.history.current_state().current.game,
+                    request.player_index,
+                    request.query,
+                );
+                // Player may have given up waiting already
+                let _ = request.response_sender.send(response);
             }
         }
 
@@ -139,17 +249,35 @@ where
         }
 
         let max_time = (history_len.max(2) - 1) as f64;
-        self.ui.set_time(
-            self.current_tick.min(max_time),
-            max_time,
-            self.renderer.default_tps(),
-        );
+        self.ui
+            .set_time(self.current_tick.min(max_time), max_time, ticks_per_second);
+        self.ui.set_breakpoints(breakpoints);
+        if self.ui.take_dropped_debug_data_dismissed() {
+            self.history.dismiss_dropped_debug_data();
+        }
+        self.ui
+            .set_dropped_debug_data(self.history.dropped_debug_data());
+        self.ui
+            .set_debug_layers(self.history.debug_players(), self.history.debug_layers());
         self.ui_controller
-            .update(&mut self.ui.ui(self.renderer.default_tps()), delta_time);
+            .update(&mut self.ui.ui(ticks_per_second), delta_time);
 
-        for event in self.history.go_to(self.current_tick, process_events) {
-            self.renderer.process_event(&event);
+        self.history
+            .set_debug_data_filter(self.debug_data_filter.borrow().clone());
+        match self.history.go_to(self.current_tick, process_events) {
+            Ok(events) => {
+                for event in events {
+                    self.renderer.process_event(&event);
+                }
+            }
+            Err(e) => {
+                if !self.paused.get() {
+                    error!("Failed to read game history: {}", e);
+                    self.paused.set(true);
+                }
+            }
         }
+        self.ui.set_logs(&self.history);
 
         if let Some(processor) = &self.processor {
             *self.debug_state.lock().unwrap() = (0..processor.player_count())
@@ -165,13 +293,15 @@ where
     fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
         let state = self.history.current_state();
         self.renderer.draw(state, framebuffer);
+        let ticks_per_second = self.ticks_per_second();
         self.ui_controller
-            .draw(&mut self.ui.ui(self.renderer.default_tps()), framebuffer);
+            .draw(&mut self.ui.ui(ticks_per_second), framebuffer);
     }
     fn handle_event(&mut self, event: geng::Event) {
+        let ticks_per_second = self.ticks_per_second();
         if !self
             .ui_controller
-            .handle_event(&mut self.ui.ui(self.renderer.default_tps()), event.clone())
+            .handle_event(&mut self.ui.ui(ticks_per_second), event.clone())
         {
             if !match event {
                 geng::Event::KeyDown { key } => match key {
@@ -232,3 +362,10 @@ where
         }
     }
 }
+
+impl<G: Game, R: Renderer<G>> Drop for GameScreen<G, R> {
+    fn drop(&mut self) {
+        // Dropping senders so that players waiting for an answer are not blocked forever
+        *self.debug_queries.lock().unwrap() = None;
+    }
+}
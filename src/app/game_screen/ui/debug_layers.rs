@@ -0,0 +1,104 @@
+use super::*;
+
+use std::collections::BTreeMap;
+
+pub struct DebugLayersPanel {
+    theme: Rc<ui::Theme>,
+    filter: Rc<RefCell<DebugDataFilter>>,
+    shown: bool,
+    toggle_button: ui::Button,
+    player_buttons: BTreeMap<usize, ui::Button>,
+    layer_buttons: BTreeMap<String, ui::Button>,
+}
+
+fn toggle<T: Eq + std::hash::Hash>(disabled: &mut HashSet<T>, value: T) {
+    if !disabled.remove(&value) {
+        disabled.insert(value);
+    }
+}
+
+fn on_off(enabled: bool) -> String {
+    translate(if enabled { "on" } else { "off" }).to_owned()
+}
+
+impl DebugLayersPanel {
+    pub fn new(theme: &Rc<ui::Theme>, filter: &Rc<RefCell<DebugDataFilter>>) -> Self {
+        Self {
+            theme: theme.clone(),
+            filter: filter.clone(),
+            shown: false,
+            toggle_button: ui::Button::new(),
+            player_buttons: BTreeMap::new(),
+            layer_buttons: BTreeMap::new(),
+        }
+    }
+    pub fn set_layers(&mut self, players: Vec<usize>, layers: Vec<String>) {
+        for player_index in players {
+            self.player_buttons
+                .entry(player_index)
+                .or_insert_with(ui::Button::new);
+        }
+        for layer in layers {
+            self.layer_buttons
+                .entry(layer)
+                .or_insert_with(ui::Button::new);
+        }
+    }
+    pub fn ui<'a>(&'a mut self) -> impl ui::Widget + 'a {
+        use ui::*;
+        if self.toggle_button.clicked() {
+            self.shown = !self.shown;
+        }
+        let mut filter = self.filter.borrow_mut();
+        for (&player_index, button) in &mut self.player_buttons {
+            if button.clicked() {
+                toggle(&mut filter.disabled_players, player_index);
+            }
+        }
+        for (layer, button) in &mut self.layer_buttons {
+            if button.clicked() {
+                toggle(&mut filter.disabled_layers, layer.clone());
+            }
+        }
+        let theme = &self.theme;
+        let mut widgets: Vec<Box<dyn Widget + 'a>> = Vec::new();
+        if !self.player_buttons.is_empty() || !self.layer_buttons.is_empty() {
+            widgets.push(Box::new(ui::Button::text(
+                &mut self.toggle_button,
+                translate("Debug layers"),
+                theme,
+            )));
+        }
+        if self.shown {
+            for (&player_index, button) in &mut self.player_buttons {
+                widgets.push(Box::new(ui::Button::text(
+                    button,
+                    format!(
+                        "{} {}: {}",
+                        translate("Player"),
+                        player_index + 1,
+                        on_off(!filter.disabled_players.contains(&player_index)),
+                    ),
+                    theme,
+                )));
+            }
+            for (layer, button) in &mut self.layer_buttons {
+                let name = if layer == DEFAULT_DEBUG_LAYER {
+                    translate("Default layer").to_owned()
+                } else {
+                    layer.clone()
+                };
+                widgets.push(Box::new(ui::Button::text(
+                    button,
+                    format!(
+                        "{}: {}",
+                        name,
+                        on_off(!filter.disabled_layers.contains(layer))
+                    ),
+                    theme,
+                )));
+            }
+        }
+        ui::column(widgets).uniform_padding(UI_PADDING)
+    }
+}
@@ -0,0 +1,126 @@
+use super::*;
+
+const LOG_LINES: usize = 10;
+const LOG_FONT_SIZE: f32 = 20.0;
+
+pub struct LogConsolePanel {
+    theme: Rc<ui::Theme>,
+    filter: Rc<RefCell<DebugDataFilter>>,
+    shown: bool,
+    offset: usize,
+    logs: Vec<DebugLogMessage>,
+    toggle_button: ui::Button,
+    level_button: ui::Button,
+    older_button: ui::Button,
+    newer_button: ui::Button,
+}
+
+fn level_name(level: DebugLogLevel) -> &'static str {
+    translate(match level {
+        DebugLogLevel::Debug => "debug",
+        DebugLogLevel::Info => "info",
+        DebugLogLevel::Warning => "warning",
+        DebugLogLevel::Error => "error",
+    })
+}
+
+fn level_color(level: DebugLogLevel) -> Color<f32> {
+    match level {
+        DebugLogLevel::Debug => Color::GRAY,
+        DebugLogLevel::Info => Color::WHITE,
+        DebugLogLevel::Warning => Color::YELLOW,
+        DebugLogLevel::Error => Color::RED,
+    }
+}
+
+fn next_level(level: DebugLogLevel) -> DebugLogLevel {
+    match level {
+        DebugLogLevel::Debug => DebugLogLevel::Info,
+        DebugLogLevel::Info => DebugLogLevel::Warning,
+        DebugLogLevel::Warning => DebugLogLevel::Error,
+        DebugLogLevel::Error => DebugLogLevel::Debug,
+    }
+}
+
+impl LogConsolePanel {
+    pub fn new(theme: &Rc<ui::Theme>, filter: &Rc<RefCell<DebugDataFilter>>) -> Self {
+        Self {
+            theme: theme.clone(),
+            filter: filter.clone(),
+            shown: false,
+            offset: 0,
+            logs: Vec::new(),
+            toggle_button: ui::Button::new(),
+            level_button: ui::Button::new(),
+            older_button: ui::Button::new(),
+            newer_button: ui::Button::new(),
+        }
+    }
+    pub fn set_logs<G: Game, T: RendererData<G>>(&mut self, history: &History<G, T>) {
+        self.logs = history.logs(self.offset, LOG_LINES);
+        if self.logs.is_empty() && self.offset > 0 {
+            self.offset = 0;
+            self.logs = history.logs(self.offset, LOG_LINES);
+        }
+    }
+    pub fn ui<'a>(&'a mut self) -> impl ui::Widget + 'a {
+        use ui::*;
+        if self.toggle_button.clicked() {
+            self.shown = !self.shown;
+        }
+        if self.level_button.clicked() {
+            let mut filter = self.filter.borrow_mut();
+            filter.min_log_level = next_level(filter.min_log_level);
+            self.offset = 0;
+        }
+        if self.older_button.clicked() && self.logs.len() == LOG_LINES {
+            self.offset += 1;
+        }
+        if self.newer_button.clicked() {
+            self.offset = self.offset.saturating_sub(1);
+        }
+        let theme = &self.theme;
+        let mut widgets: Vec<Box<dyn Widget + 'a>> = vec![Box::new(
+            ui::Button::text(&mut self.toggle_button, translate("Log"), theme)
+                .align(vec2(1.0, 0.5)),
+        )];
+        if self.shown {
+            widgets.push(Box::new(
+                ui::row![
+                    ui::Button::text(
+                        &mut self.level_button,
+                        format!(
+                            "{}: {}",
+                            translate("Level"),
+                            level_name(self.filter.borrow().min_log_level)
+                        ),
+                        theme,
+                    ),
+                    ui::Button::text(&mut self.older_button, translate("older"), theme)
+                        .padding_left(UI_PADDING),
+                    ui::Button::text(&mut self.newer_button, translate("newer"), theme)
+                        .padding_left(UI_PADDING),
+                ]
+                .align(vec2(1.0, 0.5)),
+            ));
+            for message in &self.logs {
+                widgets.push(Box::new(
+                    ui::Text::new(
+                        format!(
+                            "[{}] {} {}: {}",
+                            message.tick,
+                            translate("Player"),
+                            message.player_index + 1,
+                            message.message,
+                        ),
+                        &theme.font,
+                        LOG_FONT_SIZE,
+                        level_color(message.level),
+                    )
+                    .align(vec2(1.0, 0.5)),
+                ));
+            }
+        }
+        ui::column(widgets).uniform_padding(UI_PADDING)
+    }
+}
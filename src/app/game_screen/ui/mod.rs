@@ -1,13 +1,17 @@
 use super::*;
 use geng::ui;
 
+mod debug_layers;
 mod fullscreen_button;
+mod log_console;
 mod play_stop_button;
 mod timeline;
 mod view_speed;
 mod volume;
 
+use debug_layers::*;
 use fullscreen_button::*;
+use log_console::*;
 use play_stop_button::*;
 use timeline::*;
 pub use view_speed::*;
@@ -41,6 +45,12 @@ pub struct UI {
     view_speed: ViewSpeedControl,
     #[allow(dead_code)]
     volume: VolumeControl, // TODO: not dead
+    theme: Rc<ui::Theme>,
+    dropped_debug_data: Option<DroppedDebugData>,
+    dismiss_dropped_debug_data_button: ui::Button,
+    dropped_debug_data_dismissed: bool,
+    debug_layers: DebugLayersPanel,
+    log_console: LogConsolePanel,
 }
 
 impl UI {
@@ -49,6 +59,7 @@ impl UI {
         paused: &Rc<Cell<bool>>,
         view_speed_modifier: &Rc<Cell<f64>>,
         volume: &Rc<Cell<f64>>,
+        debug_data_filter: &Rc<RefCell<DebugDataFilter>>,
     ) -> Self {
         let theme = Rc::new(ui::Theme::default(geng));
         let theme = &theme;
@@ -58,6 +69,12 @@ impl UI {
             timeline: Timeline::new(theme),
             view_speed: ViewSpeedControl::new(theme, view_speed_modifier),
             volume: VolumeControl::new(theme, volume),
+            debug_layers: DebugLayersPanel::new(theme, debug_data_filter),
+            log_console: LogConsolePanel::new(theme, debug_data_filter),
+            theme: theme.clone(),
+            dropped_debug_data: None,
+            dismiss_dropped_debug_data_button: ui::Button::new(),
+            dropped_debug_data_dismissed: false,
         }
     }
 
@@ -69,15 +86,85 @@ impl UI {
         self.timeline.set_time(time, max_time, ticks_per_second);
     }
 
+    pub fn set_breakpoints(&mut self, breakpoints: &[DebugBreakpoint]) {
+        self.timeline.set_breakpoints(breakpoints);
+    }
+
+    pub fn set_dropped_debug_data(&mut self, dropped_debug_data: Option<DroppedDebugData>) {
+        self.dropped_debug_data = dropped_debug_data;
+    }
+
+    /// Whether the dropped debug data warning was dismissed since last call
+    pub fn take_dropped_debug_data_dismissed(&mut self) -> bool {
+        mem::replace(&mut self.dropped_debug_data_dismissed, false)
+    }
+
+    fn dropped_debug_data_warning(&self) -> String {
+        match self.dropped_debug_data {
+            Some(dropped) => format!(
+                "{} ({}): {} {}",
+                translate("Debug data limit reached"),
+                translate(match dropped.limit {
+                    DebugDataLimit::PlayerTick => "per player per tick",
+                    DebugDataLimit::Tick => "per tick",
+                    DebugDataLimit::Total => "total",
+                }),
+                dropped.count,
+                translate("commands dropped"),
+            ),
+            None => String::new(),
+        }
+    }
+
+    pub fn set_debug_layers(&mut self, players: Vec<usize>, layers: Vec<String>) {
+        self.debug_layers.set_layers(players, layers);
+    }
+
+    pub fn set_logs<G: Game, T: RendererData<G>>(&mut self, history: &History<G, T>) {
+        self.log_console.set_logs(history);
+    }
+
     pub fn ui<'a>(&'a mut self, default_tps: f64) -> impl ui::Widget + 'a {
         use ui::*;
-        geng::ui::row![
-            self.play_stop_button.ui(),
-            self.timeline.ui(),
-            self.view_speed.ui(default_tps),
-            // TODO: self.volume.ui(),
-            self.fullscreen_button.ui(),
+        if self.dismiss_dropped_debug_data_button.clicked() {
+            self.dropped_debug_data = None;
+            self.dropped_debug_data_dismissed = true;
+        }
+        let breakpoints_text = self.timeline.current_breakpoints().unwrap_or_default();
+        let dropped_debug_data_warning = self.dropped_debug_data_warning();
+        let mut messages: Vec<Box<dyn Widget + 'a>> = vec![
+            Box::new(ui::Text::new(
+                breakpoints_text,
+                &self.theme.font,
+                UI_SIZE as f32,
+                Color::RED,
+            )),
+            Box::new(ui::Text::new(
+                dropped_debug_data_warning,
+                &self.theme.font,
+                UI_SIZE as f32 / 2.0,
+                Color::YELLOW,
+            )),
+        ];
+        if self.dropped_debug_data.is_some() {
+            messages.push(Box::new(ui::Button::text(
+                &mut self.dismiss_dropped_debug_data_button,
+                translate("Dismiss"),
+                &self.theme,
+            )));
+        }
+        geng::ui::stack![
+            geng::ui::row![
+                self.play_stop_button.ui(),
+                self.timeline.ui(),
+                self.view_speed.ui(default_tps),
+                // TODO: self.volume.ui(),
+                self.fullscreen_button.ui(),
+            ]
+            .align(vec2(0.5, 0.0)),
+            self.debug_layers.ui().align(vec2(0.0, 1.0)),
+            self.log_console.ui().align(vec2(1.0, 1.0)),
+            ui::column(messages).align(vec2(0.5, 1.0)),
         ]
-        .align(vec2(0.5, 0.0))
     }
 }
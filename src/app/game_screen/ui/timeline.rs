@@ -40,11 +40,45 @@ impl TimeLabel {
     }
 }
 
+struct BreakpointMarkers<'a> {
+    geng: &'a Rc<Geng>,
+    core: &'a mut ui::WidgetCore,
+    breakpoints: &'a [DebugBreakpoint],
+    max_time: f64,
+}
+
+impl ui::Widget for BreakpointMarkers<'_> {
+    fn core(&self) -> &ui::WidgetCore {
+        self.core
+    }
+    fn core_mut(&mut self) -> &mut ui::WidgetCore {
+        self.core
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let position = self.core.position().map(|x| x as f32);
+        let margin = position.height() / 6.0;
+        for breakpoint in self.breakpoints {
+            let x = position.x_min
+                + margin
+                + (breakpoint.tick as f64 / self.max_time) as f32
+                    * (position.width() - margin * 2.0);
+            self.geng.draw_2d().quad(
+                framebuffer,
+                AABB::from_corners(vec2(x - 1.0, position.y_min), vec2(x + 1.0, position.y_max)),
+                Color::RED,
+            );
+        }
+    }
+}
+
 #[derive(Deref)]
 pub struct Timeline {
     #[deref]
     slider: ui::Slider,
     label: TimeLabel,
+    geng: Rc<Geng>,
+    markers: ui::WidgetCore,
+    breakpoints: Vec<DebugBreakpoint>,
     time: f64,
     max_time: f64,
     ticks_per_second: f64,
@@ -55,6 +89,9 @@ impl Timeline {
         Self {
             slider: ui::Slider::new(theme),
             label: TimeLabel::new(theme),
+            geng: theme.geng().clone(),
+            markers: ui::WidgetCore::new(),
+            breakpoints: Vec::new(),
             time: 0.0,
             max_time: 1.0,
             ticks_per_second: 1.0,
@@ -67,17 +104,24 @@ impl Timeline {
             self.label
                 .ui(*time, self.ticks_per_second)
                 .uniform_padding(UI_PADDING),
-            self.slider
-                .ui(
+            stack![
+                BreakpointMarkers {
+                    geng: &self.geng,
+                    core: &mut self.markers,
+                    breakpoints: &self.breakpoints,
+                    max_time: self.max_time,
+                },
+                self.slider.ui(
                     *time,
                     0.0..=self.max_time,
                     Box::new(move |value| *time = value),
-                )
-                .constraints_override(ui::widget::Constraints {
-                    min_size: vec2(UI_SIZE, UI_SIZE),
-                    flex: vec2(1.0, 0.0),
-                })
-                .uniform_padding(UI_PADDING),
+                ),
+            ]
+            .constraints_override(ui::widget::Constraints {
+                min_size: vec2(UI_SIZE, UI_SIZE),
+                flex: vec2(1.0, 0.0),
+            })
+            .uniform_padding(UI_PADDING),
         ]
     }
     pub fn set_time(&mut self, time: f64, max_time: f64, ticks_per_second: f64) {
@@ -85,6 +129,32 @@ impl Timeline {
         self.max_time = max_time;
         self.ticks_per_second = ticks_per_second;
     }
+    /// Breakpoints are only ever added, so only new ones are copied
+    pub fn set_breakpoints(&mut self, breakpoints: &[DebugBreakpoint]) {
+        if breakpoints.len() > self.breakpoints.len() {
+            self.breakpoints
+                .extend_from_slice(&breakpoints[self.breakpoints.len()..]);
+        }
+    }
+    /// Labels of breakpoints at current tick
+    pub fn current_breakpoints(&self) -> Option<String> {
+        let tick = self.time.ceil() as usize;
+        let labels: Vec<&str> = self
+            .breakpoints
+            .iter()
+            .filter(|breakpoint| breakpoint.tick == tick)
+            .map(|breakpoint| breakpoint.label.as_str())
+            .collect();
+        if labels.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "{}: {}",
+                translate("Breakpoint"),
+                labels.join(", ")
+            ))
+        }
+    }
     pub fn change(&self) -> Option<f64> {
         if self.captured() {
             Some(self.time)
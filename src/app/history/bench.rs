@@ -0,0 +1,104 @@
+use super::*;
+
+use std::time::Instant;
+
+/// State with a lot of values, few of them changing every tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BenchState {
+    values: Vec<u64>,
+}
+
+impl Diff for BenchState {
+    type Delta = Vec<(usize, u64)>;
+    fn diff(&self, to: &Self) -> Self::Delta {
+        self.values
+            .iter()
+            .zip(&to.values)
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(index, (_, &new))| (index, new))
+            .collect()
+    }
+    fn update(&mut self, delta: &Self::Delta) {
+        for &(index, value) in delta {
+            self.values[index] = value;
+        }
+    }
+}
+
+/// Deterministic random numbers, so that every policy seeks the same ticks
+struct Lcg(u64);
+
+impl Lcg {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+const TICKS: usize = 10_000;
+const VALUES: usize = 1_000;
+const CHANGES_PER_TICK: usize = 10;
+const SEEKS: usize = 1_000;
+
+fn build_history(keyframes: HistoryKeyframes) -> DiffHistory<BenchState> {
+    let mut rng = Lcg(42);
+    let mut history = DiffHistory::new(BenchState {
+        values: vec![0; VALUES],
+    });
+    history.keyframes_policy = keyframes;
+    for _ in 1..TICKS {
+        history.push_mut(|state| {
+            for _ in 0..CHANGES_PER_TICK {
+                let index = rng.next_u64() as usize % VALUES;
+                state.values[index] = rng.next_u64();
+            }
+        });
+    }
+    history
+}
+
+/// Time of seeking to random ticks with every keyframes policy.
+/// Run with `cargo test --release seek_history -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn seek_history() {
+    let policies = [
+        HistoryKeyframes::Auto,
+        HistoryKeyframes::Interval(1),
+        HistoryKeyframes::Interval(10),
+        HistoryKeyframes::Interval(100),
+        HistoryKeyframes::Budget(4 << 10),
+        HistoryKeyframes::Budget(64 << 10),
+    ];
+    for &keyframes in &policies {
+        let history = build_history(keyframes);
+        for &cache_size in &[0, 16] {
+            let mut rng = Lcg(7);
+            let mut cache = SnapshotCache::new(cache_size);
+            let mut snapshot = HistorySnapshot {
+                value: BenchState {
+                    values: vec![0; VALUES],
+                },
+                tick: 0,
+            };
+            let timer = Instant::now();
+            for _ in 0..SEEKS {
+                let tick = rng.next_u64() as usize % TICKS;
+                snapshot.go_to(tick, &history, &mut cache).unwrap();
+            }
+            let elapsed = timer.elapsed();
+            println!(
+                "{:?}, cache {}: {} keyframes, {} KiB, {:.1} us per seek",
+                keyframes,
+                cache_size,
+                history.keyframes.len(),
+                history.entries.memory_size() >> 10,
+                elapsed.as_secs_f64() * 1e6 / SEEKS as f64,
+            );
+        }
+    }
+}
@@ -1,22 +1,28 @@
 use super::*;
 
 impl<G: Game, T: RendererData<G>> History<G, T> {
-    pub fn load(path: &str) -> impl Future<Output = Self> {
+    pub fn load(path: &str, options: HistoryOptions) -> impl Future<Output = Self> {
         fn load<G: Game, T: RendererData<G>>(
             path: &str,
+            options: HistoryOptions,
         ) -> std::io::Result<impl Future<Output = History<G, T>>> {
-            let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
-            let initial_state = G::read_from(&mut reader)?;
-            let history = History::<G, T>::new(initial_state.clone());
-            let mut tick_handler = history.tick_handler();
-            let mut current_state = initial_state;
+            let mut replay = ReplayReader::<G, _>::new_with_desync_warnings(
+                std::io::BufReader::new(std::fs::File::open(path)?),
+            )?;
+            let history = History::<G, T>::new(replay.game().clone(), &options);
+            let shared_state = history.shared_state.clone();
+            {
+                let mut shared_state = shared_state.lock().unwrap();
+                shared_state.load_global_debug_data(&replay);
+                shared_state.load_debug_data(&replay);
+            }
             std::thread::spawn(move || {
                 let mut f = move || -> std::io::Result<()> {
-                    while !reader.fill_buf()?.is_empty() {
-                        let events = Vec::<G::Event>::read_from(&mut reader)?;
-                        let delta = G::Delta::read_from(&mut reader)?;
-                        current_state.update(&delta);
-                        tick_handler(&current_state, events);
+                    while !replay.finished()? {
+                        let events = replay.next_tick()?;
+                        let mut shared_state = shared_state.lock().unwrap();
+                        shared_state.push(replay.game().clone(), events);
+                        shared_state.load_debug_data(&replay);
                     }
                     Ok(())
                 };
@@ -24,6 +30,6 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
             });
             Ok(futures::future::ready(history))
         }
-        load::<G, T>(path).expect("Failed to load replay")
+        load::<G, T>(path, options).expect("Failed to load replay")
     }
 }
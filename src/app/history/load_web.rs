@@ -1,9 +1,10 @@
 use super::*;
 
 impl<G: Game, T: RendererData<G>> History<G, T> {
-    pub fn load(path: &str) -> impl Future<Output = Self> {
+    pub fn load(path: &str, options: HistoryOptions) -> impl Future<Output = Self> {
         fn load<G: Game, T: RendererData<G>>(
             path: &str,
+            options: HistoryOptions,
         ) -> Result<impl Future<Output = History<G, T>>, Box<dyn std::error::Error>> {
             let xhr = web_sys::XmlHttpRequest::new().unwrap();
             xhr.open("GET", path).unwrap();
@@ -21,16 +22,18 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
                             .as_ref(),
                     )
                     .to_vec();
-                    let mut reader = data.as_slice();
-                    let initial_state = G::read_from(&mut reader)?;
-                    let history = History::new(initial_state.clone());
-                    let mut current_state = initial_state;
-                    let mut tick_handler = history.tick_handler();
-                    while !BufRead::fill_buf(&mut reader)?.is_empty() {
-                        let events = Vec::<G::Event>::read_from(&mut reader)?;
-                        let delta = G::Delta::read_from(&mut reader)?;
-                        current_state.update(&delta);
-                        tick_handler(&current_state, events);
+                    let mut replay =
+                        ReplayReader::<G, _>::new_with_desync_warnings(data.as_slice())?;
+                    let history = History::<G, T>::new(replay.game().clone(), &options);
+                    {
+                        let mut shared_state = history.shared_state.lock().unwrap();
+                        shared_state.load_global_debug_data(&replay);
+                        shared_state.load_debug_data(&replay);
+                        while !replay.finished()? {
+                            let events = replay.next_tick()?;
+                            shared_state.push(replay.game().clone(), events);
+                            shared_state.load_debug_data(&replay);
+                        }
                     }
                     let _ = sender.send(history);
                     Ok(())
@@ -53,6 +56,6 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
             handler.forget(); // TODO: not forget
             Ok(receiver.map(|result| result.expect("Failed to load replay")))
         }
-        load::<G, T>(path).expect("Failed to load replay")
+        load::<G, T>(path, options).expect("Failed to load replay")
     }
 }
@@ -1,18 +1,59 @@
 use super::*;
 
+use std::collections::{BTreeSet, VecDeque};
+
+#[cfg(test)]
+mod bench;
 #[cfg(not(target_arch = "wasm32"))]
 mod load_native;
 #[cfg(target_arch = "wasm32")]
 mod load_web;
 mod save;
+mod spill;
+
+use spill::*;
 
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 enum DiffEntry<T: Diff> {
     Value(T),
     Delta(T::Delta),
 }
 
+/// When history stores a full value (keyframe) instead of a delta.
+/// More keyframes use more memory, but make seeking faster
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum HistoryKeyframes {
+    /// When deltas since last keyframe get bigger than the value itself
+    Auto,
+    /// Every given number of ticks
+    Interval(usize),
+    /// When deltas since last keyframe get bigger than given number of bytes
+    Budget(u64),
+}
+
+impl Default for HistoryKeyframes {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// Settings of a history, applied before anything is added to it
+#[derive(Debug, Clone, Default)]
+pub struct HistoryOptions {
+    pub keyframes: HistoryKeyframes,
+    /// Number of recently visited states kept to speed up seeking, 0 to disable
+    pub snapshot_cache_size: usize,
+    /// Limit memory used by game history and debug data, moving older ticks to a temporary file
+    pub memory_budget: Option<u64>,
+    pub debug_data_limits: DebugDataLimits,
+}
+
 struct DiffHistory<T: Diff> {
-    entries: Vec<DiffEntry<T>>,
+    entries: SpillVec<DiffEntry<T>>,
+    /// Sorted ticks of full values, known without reading spilled entries
+    keyframes: Vec<usize>,
+    keyframes_policy: HistoryKeyframes,
     last: T,
     last_deltas_size: u64,
 }
@@ -20,8 +61,12 @@ struct DiffHistory<T: Diff> {
 impl<T: Diff> DiffHistory<T> {
     fn new(initial: T) -> Self {
         let last = initial.clone();
+        let mut entries = SpillVec::new();
+        entries.push(DiffEntry::Value(initial));
         Self {
-            entries: vec![DiffEntry::Value(initial)],
+            entries,
+            keyframes: vec![0],
+            keyframes_policy: default(),
             last,
             last_deltas_size: 0,
         }
@@ -30,13 +75,28 @@ impl<T: Diff> DiffHistory<T> {
         let prev = mem::replace(&mut self.last, new_value);
         let delta = prev.diff(&self.last);
         self.last_deltas_size += bincode::serialized_size(&delta).unwrap();
-        if self.last_deltas_size > bincode::serialized_size(&self.last).unwrap() {
+        let tick = self.entries.len();
+        let keyframe = match self.keyframes_policy {
+            HistoryKeyframes::Auto => {
+                self.last_deltas_size > bincode::serialized_size(&self.last).unwrap()
+            }
+            HistoryKeyframes::Interval(interval) => {
+                tick - self.keyframes.last().unwrap() >= interval
+            }
+            HistoryKeyframes::Budget(budget) => self.last_deltas_size > budget,
+        };
+        if keyframe {
             self.entries.push(DiffEntry::Value(self.last.clone()));
+            self.keyframes.push(tick);
             self.last_deltas_size = 0;
         } else {
             self.entries.push(DiffEntry::Delta(delta));
         }
     }
+    /// Tick of the last full value not after given tick
+    fn last_keyframe(&self, tick: usize) -> usize {
+        self.keyframes[self.keyframes.partition_point(|&keyframe| keyframe <= tick) - 1]
+    }
     fn push_mut<F: FnOnce(&mut T)>(&mut self, f: F) {
         let mut new_value = self.last.clone();
         f(&mut new_value);
@@ -54,48 +114,108 @@ struct HistorySnapshot<T: Diff> {
 }
 
 impl<T: Diff> HistorySnapshot<T> {
+    /// Snapshot of the initial value of a history that has nothing else yet
     pub fn new(history: &DiffHistory<T>) -> Self {
+        assert_eq!(history.len(), 1);
         Self {
-            value: match &history.entries[0] {
-                DiffEntry::Value(value) => value.clone(),
-                _ => unreachable!(),
-            },
+            value: history.last.clone(),
             tick: 0,
         }
     }
-    pub fn go_to(&mut self, tick: usize, history: &DiffHistory<T>) {
-        let last_full = history.entries[..=tick]
+    /// Stays at a valid tick if reading history fails
+    pub fn go_to(
+        &mut self,
+        tick: usize,
+        history: &DiffHistory<T>,
+        cache: &mut SnapshotCache<T>,
+    ) -> std::io::Result<()> {
+        let keyframe = history.last_keyframe(tick);
+        let continue_forward = keyframe <= self.tick && self.tick <= tick;
+        let from = if continue_forward {
+            self.tick + 1
+        } else {
+            keyframe
+        };
+        if let Some(cached) = cache.latest_between(from, tick) {
+            *self = cached.clone();
+        } else if !continue_forward {
+            self.value = match &*history.entries.get(keyframe)? {
+                DiffEntry::Value(value) => value.clone(),
+                DiffEntry::Delta(_) => unreachable!(),
+            };
+            self.tick = keyframe;
+        }
+        for tick in self.tick + 1..=tick {
+            match &*history.entries.get(tick)? {
+                DiffEntry::Value(value) => self.value = value.clone(),
+                DiffEntry::Delta(delta) => self.value.update(delta),
+            }
+            self.tick = tick;
+        }
+        if !continue_forward {
+            cache.insert(self);
+        }
+        Ok(())
+    }
+}
+
+/// Recently visited snapshots to seek from instead of keyframes,
+/// least recently used ones are evicted first
+struct SnapshotCache<T: Diff> {
+    capacity: usize,
+    /// Most recently used are at the back
+    snapshots: VecDeque<HistorySnapshot<T>>,
+}
+
+impl<T: Diff> SnapshotCache<T> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            snapshots: VecDeque::new(),
+        }
+    }
+    /// Snapshot with greatest tick in given range
+    fn latest_between(&mut self, from: usize, to: usize) -> Option<&HistorySnapshot<T>> {
+        let index = self
+            .snapshots
             .iter()
             .enumerate()
-            .rev()
-            .find_map(|(tick, entry)| match entry {
-                DiffEntry::Value(value) => Some(Self {
-                    value: value.clone(),
-                    tick,
-                }),
-                DiffEntry::Delta(_) => None,
-            })
-            .expect("Didn't find full entry in history");
-        if tick < self.tick || self.tick < last_full.tick {
-            *self = last_full;
-        }
-        if tick > self.tick {
-            for entry in &history.entries[self.tick + 1..=tick] {
-                match entry {
-                    DiffEntry::Value(value) => self.value = value.clone(),
-                    DiffEntry::Delta(delta) => self.value.update(delta),
-                }
-            }
+            .filter(|(_, snapshot)| from <= snapshot.tick && snapshot.tick <= to)
+            .max_by_key(|(_, snapshot)| snapshot.tick)
+            .map(|(index, _)| index)?;
+        let snapshot = self.snapshots.remove(index).unwrap();
+        self.snapshots.push_back(snapshot);
+        self.snapshots.back()
+    }
+    fn insert(&mut self, snapshot: &HistorySnapshot<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.snapshots.retain(|cached| cached.tick != snapshot.tick);
+        self.snapshots.push_back(snapshot.clone());
+        if self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
         }
-        self.tick = tick;
     }
 }
 
+#[derive(Clone)]
 struct Window<T> {
     prev: Option<T>,
     current: T,
 }
 
+impl<T> Window<Arc<T>> {
+    fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.current, &other.current)
+            && match (&self.prev, &other.prev) {
+                (Some(prev), Some(other_prev)) => Arc::ptr_eq(prev, other_prev),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
 impl<T: Diff> Window<HistorySnapshot<T>> {
     pub fn new(history: &DiffHistory<T>) -> Self {
         Self {
@@ -107,36 +227,92 @@ impl<T: Diff> Window<HistorySnapshot<T>> {
             },
         }
     }
-    pub fn go_to(&mut self, tick: usize, history: &DiffHistory<T>) {
+    pub fn go_to(
+        &mut self,
+        tick: usize,
+        history: &DiffHistory<T>,
+        cache: &mut SnapshotCache<T>,
+    ) -> std::io::Result<()> {
         if tick == 0 {
             self.prev = None;
         } else {
             if let Some(precomputed) = &mut self.prev {
-                precomputed.go_to(tick - 1, history);
+                precomputed.go_to(tick - 1, history, cache)?;
             } else {
                 let mut prev = self.current.clone();
-                prev.go_to(tick - 1, history);
+                prev.go_to(tick - 1, history, cache)?;
                 self.prev = Some(prev);
             }
         }
-        self.current.go_to(tick, history);
+        self.current.go_to(tick, history, cache)
+    }
+}
+
+/// Limits on stored debug data, including logs and breakpoints, in bytes of serialized data.
+/// Data exceeding them is dropped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugDataLimits {
+    /// Data of a single player in a single tick
+    pub player_tick: u64,
+    /// Data of all players in a single tick
+    pub tick: u64,
+    /// All data in the history
+    pub total: u64,
+}
+
+impl Default for DebugDataLimits {
+    fn default() -> Self {
+        Self {
+            player_tick: 16 << 20,
+            tick: 64 << 20,
+            total: 1 << 30,
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugDataLimit {
+    PlayerTick,
+    Tick,
+    Total,
+}
+
+/// Debug data dropped because of the limits
+#[derive(Debug, Clone, Copy)]
+pub struct DroppedDebugData {
+    /// Limit hit most recently
+    pub limit: DebugDataLimit,
+    /// Number of dropped commands
+    pub count: usize,
+}
+
+fn debug_data_size<G: Game>(data: &G::DebugData) -> u64 {
+    bincode::serialized_size(data).unwrap()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct DebugDataStorage<G: Game> {
-    queued: Vec<G::DebugData>,
+    queued: Vec<(String, G::DebugData)>,
+    queued_size: u64,
     cleared: bool,
-    current: Vec<G::DebugData>,
+    cleared_layers: HashSet<String>,
+    /// Size of current data in `cleared_layers`
+    cleared_layers_size: u64,
+    current: Vec<(String, G::DebugData)>,
+    current_size: u64,
     ready: bool,
     auto_flush: bool,
 }
 
 impl<'a, G: Game> IntoIterator for &'a DebugDataStorage<G> {
     type Item = &'a G::DebugData;
-    type IntoIter = <&'a Vec<G::DebugData> as IntoIterator>::IntoIter;
+    type IntoIter = std::iter::Map<
+        std::slice::Iter<'a, (String, G::DebugData)>,
+        fn(&'a (String, G::DebugData)) -> &'a G::DebugData,
+    >;
     fn into_iter(self) -> Self::IntoIter {
-        self.current.iter()
+        self.current.iter().map(|(_, data)| data)
     }
 }
 
@@ -144,19 +320,75 @@ impl<G: Game> DebugDataStorage<G> {
     fn new() -> Self {
         Self {
             queued: Vec::new(),
+            queued_size: 0,
             cleared: false,
+            cleared_layers: HashSet::new(),
+            cleared_layers_size: 0,
             current: Vec::new(),
+            current_size: 0,
             auto_flush: true,
             ready: false,
         }
     }
-    fn handle(&mut self, command: DebugCommand<G>) {
+    /// Data of a single layer
+    pub fn layer<'a>(&'a self, layer: &'a str) -> impl Iterator<Item = &'a G::DebugData> + 'a {
+        self.current
+            .iter()
+            .filter(move |(data_layer, _)| data_layer == layer)
+            .map(|(_, data)| data)
+    }
+    /// Names of layers that have data
+    pub fn layers(&self) -> impl Iterator<Item = &str> {
+        self.current.iter().map(|(layer, _)| layer.as_str())
+    }
+    /// Serialized size of the data it will have after flush
+    fn size(&self) -> u64 {
+        let current_size = if self.cleared {
+            0
+        } else {
+            self.current_size - self.cleared_layers_size
+        };
+        current_size + self.queued_size
+    }
+    /// `data_size` is the serialized size of data being added, if any
+    fn handle(&mut self, command: DebugCommand<G>, data_size: u64) {
         match command {
-            DebugCommand::Add { data } => self.queued.push(data),
+            DebugCommand::Add { data } => {
+                self.queued.push((DEFAULT_DEBUG_LAYER.to_owned(), data));
+                self.queued_size += data_size;
+            }
+            DebugCommand::AddToLayer { layer, data } => {
+                self.queued.push((layer, data));
+                self.queued_size += data_size;
+            }
             DebugCommand::Clear => {
                 self.cleared = true;
+                self.cleared_layers.clear();
+                self.cleared_layers_size = 0;
                 self.queued.clear();
+                self.queued_size = 0;
+            }
+            DebugCommand::ClearLayer { layer } => {
+                let queued_size = &mut self.queued_size;
+                self.queued.retain(|(data_layer, data)| {
+                    if *data_layer == layer {
+                        *queued_size -= debug_data_size::<G>(data);
+                        false
+                    } else {
+                        true
+                    }
+                });
+                if !self.cleared && !self.cleared_layers.contains(&layer) {
+                    self.cleared_layers_size += self
+                        .current
+                        .iter()
+                        .filter(|(data_layer, _)| *data_layer == layer)
+                        .map(|(_, data)| debug_data_size::<G>(data))
+                        .sum::<u64>();
+                }
+                self.cleared_layers.insert(layer);
             }
+            DebugCommand::Log { .. } | DebugCommand::Breakpoint { .. } => {}
             DebugCommand::SetAutoFlush { enable } => self.auto_flush = enable,
             DebugCommand::Flush => self.flush(),
         }
@@ -168,10 +400,92 @@ impl<G: Game> DebugDataStorage<G> {
         if self.cleared {
             self.cleared = false;
             self.current.clear();
+            self.current_size = 0;
+        }
+        if !self.cleared_layers.is_empty() {
+            self.cleared_layers_size = 0;
+            let cleared_layers = mem::replace(&mut self.cleared_layers, HashSet::new());
+            let current_size = &mut self.current_size;
+            self.current.retain(|(layer, data)| {
+                if cleared_layers.contains(layer) {
+                    *current_size -= debug_data_size::<G>(data);
+                    false
+                } else {
+                    true
+                }
+            });
         }
         self.current.extend(self.queued.drain(..));
+        self.current_size += mem::replace(&mut self.queued_size, 0);
         self.ready = true;
     }
+    /// Copy of current data of enabled layers only, to be rendered.
+    /// Queued data and sizes are not copied
+    fn filtered(&self, layer_enabled: impl Fn(&str) -> bool) -> Self {
+        Self {
+            current: self
+                .current
+                .iter()
+                .filter(|(layer, _)| layer_enabled(layer))
+                .cloned()
+                .collect(),
+            ready: self.ready,
+            ..Self::new()
+        }
+    }
+    fn to_replay(storages: &HashMap<usize, Self>) -> PlayersDebugData<G> {
+        storages
+            .iter()
+            .map(|(&player_index, storage)| (player_index, storage.current.clone()))
+            .collect()
+    }
+    fn from_replay(data: &PlayersDebugData<G>) -> HashMap<usize, Self> {
+        data.iter()
+            .map(|(&player_index, data)| {
+                let mut storage = Self::new();
+                storage.current = data.clone();
+                storage.current_size = data
+                    .iter()
+                    .map(|(_, data)| debug_data_size::<G>(data))
+                    .sum();
+                storage.ready = true;
+                (player_index, storage)
+            })
+            .collect()
+    }
+}
+
+/// Which players and layers of debug data are shown
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugDataFilter {
+    pub disabled_players: HashSet<usize>,
+    pub disabled_layers: HashSet<String>,
+    /// Log messages with lower level are hidden
+    pub min_log_level: DebugLogLevel,
+}
+
+impl DebugDataFilter {
+    pub fn shows_log(&self, message: &DebugLogMessage) -> bool {
+        message.level >= self.min_log_level
+            && !self.disabled_players.contains(&message.player_index)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.disabled_players.is_empty() && self.disabled_layers.is_empty()
+    }
+    pub fn apply<G: Game>(
+        &self,
+        data: &HashMap<usize, DebugDataStorage<G>>,
+    ) -> HashMap<usize, DebugDataStorage<G>> {
+        data.iter()
+            .filter(|(player_index, _)| !self.disabled_players.contains(player_index))
+            .map(|(&player_index, storage)| {
+                (
+                    player_index,
+                    storage.filtered(|layer| !self.disabled_layers.contains(layer)),
+                )
+            })
+            .collect()
+    }
 }
 
 struct HistorySharedState<G: Game, T: RendererData<G>> {
@@ -179,32 +493,154 @@ struct HistorySharedState<G: Game, T: RendererData<G>> {
     renderer_data: DiffHistory<T>,
     global_debug_data: HashMap<usize, DebugDataStorage<G>>,
     last_debug_data: HashMap<usize, DebugDataStorage<G>>,
-    debug_data: Vec<Arc<HashMap<usize, DebugDataStorage<G>>>>,
-    events: Vec<Arc<Vec<G::Event>>>,
+    /// Changed every time `last_debug_data` is
+    last_debug_data_version: u64,
+    debug_data: SpillVec<HashMap<usize, DebugDataStorage<G>>>,
+    debug_players: BTreeSet<usize>,
+    debug_layers: BTreeSet<String>,
+    logs: Vec<DebugLogMessage>,
+    breakpoints: Vec<DebugBreakpoint>,
+    debug_data_limits: DebugDataLimits,
+    /// Size of debug data of all ticks except last
+    stored_debug_data_size: u64,
+    /// Size of logs and breakpoints sent by every player on last tick
+    last_debug_messages_size: HashMap<usize, u64>,
+    /// Size of all logs and breakpoints
+    debug_messages_size: u64,
+    dropped_debug_data: Option<DroppedDebugData>,
+    events: SpillVec<Vec<G::Event>>,
+    /// Serialized size of game states, renderer data, events and debug data to keep in memory
+    memory_budget: Option<u64>,
+    spill_file: Option<Arc<SpillFile>>,
 }
 
 impl<G: Game, T: RendererData<G>> HistorySharedState<G, T> {
-    fn new(initial_game: G) -> Self {
+    fn new(initial_game: G, options: &HistoryOptions) -> Self {
         let initial_renderer_data = T::new(&initial_game);
-        Self {
+        let mut shared_state = Self {
             game: DiffHistory::new(initial_game),
             renderer_data: DiffHistory::new(initial_renderer_data),
             last_debug_data: HashMap::new(),
+            last_debug_data_version: 0,
             global_debug_data: HashMap::new(),
-            debug_data: Vec::new(),
-            events: Vec::new(),
-        }
+            debug_data: SpillVec::new(),
+            debug_players: BTreeSet::new(),
+            debug_layers: BTreeSet::new(),
+            logs: Vec::new(),
+            breakpoints: Vec::new(),
+            debug_data_limits: options.debug_data_limits.clone(),
+            stored_debug_data_size: 0,
+            last_debug_messages_size: HashMap::new(),
+            debug_messages_size: 0,
+            dropped_debug_data: None,
+            events: SpillVec::new(),
+            memory_budget: None,
+            spill_file: None,
+        };
+        shared_state.game.keyframes_policy = options.keyframes;
+        shared_state.renderer_data.keyframes_policy = options.keyframes;
+        shared_state.set_memory_budget(options.memory_budget);
+        shared_state
     }
     fn push(&mut self, game: G, events: Vec<G::Event>) {
         let prev_game = &self.game.last;
         self.renderer_data
             .push_mut(|data| RendererData::update(data, &events, &prev_game, &game));
         self.game.push(game);
-        self.events.push(Arc::new(events));
-        self.debug_data.push(Arc::new(mem::replace(
-            &mut self.last_debug_data,
-            HashMap::new(),
-        )));
+        self.events.push(events);
+        self.stored_debug_data_size += Self::debug_data_size(&self.last_debug_data);
+        self.debug_data
+            .push(mem::replace(&mut self.last_debug_data, HashMap::new()));
+        self.last_debug_messages_size.clear();
+        self.last_debug_data_version += 1;
+        self.enforce_memory_budget();
+    }
+    fn set_memory_budget(&mut self, budget: Option<u64>) {
+        self.memory_budget = budget;
+        if budget.is_some() && self.spill_file.is_none() {
+            match SpillFile::create() {
+                Ok(file) => self.spill_file = Some(Arc::new(file)),
+                Err(e) => warn!("Failed to create history spill file: {}", e),
+            }
+            self.game.entries.set_file(self.spill_file.clone());
+            self.renderer_data.entries.set_file(self.spill_file.clone());
+            self.events.set_file(self.spill_file.clone());
+            self.debug_data.set_file(self.spill_file.clone());
+        }
+        self.enforce_memory_budget();
+    }
+    /// Move oldest entries to disk until what is left in memory fits the budget
+    fn enforce_memory_budget(&mut self) {
+        let budget = match self.memory_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+        loop {
+            let sizes = [
+                self.game.entries.memory_size(),
+                self.renderer_data.entries.memory_size(),
+                self.events.memory_size(),
+                self.debug_data.memory_size(),
+            ];
+            if sizes.iter().sum::<u64>() <= budget {
+                break;
+            }
+            // Spill from the biggest one
+            let biggest = (0..sizes.len()).max_by_key(|&index| sizes[index]).unwrap();
+            let spilled = match biggest {
+                0 => self.game.entries.spill_oldest(),
+                1 => self.renderer_data.entries.spill_oldest(),
+                2 => self.events.spill_oldest(),
+                _ => self.debug_data.spill_oldest(),
+            };
+            if !spilled {
+                break;
+            }
+        }
+    }
+    fn debug_data_size(data: &HashMap<usize, DebugDataStorage<G>>) -> u64 {
+        data.values().map(|storage| storage.size()).sum()
+    }
+    /// Find out which limit would be exceeded by adding data of given size
+    fn exceeded_debug_data_limit(
+        &self,
+        player_index: usize,
+        global: bool,
+        data_size: u64,
+    ) -> Option<DebugDataLimit> {
+        let limits = &self.debug_data_limits;
+        let data = if global {
+            &self.global_debug_data
+        } else {
+            &self.last_debug_data
+        };
+        let (player_messages_size, tick_messages_size) = if global {
+            (0, 0)
+        } else {
+            (
+                self.last_debug_messages_size
+                    .get(&player_index)
+                    .copied()
+                    .unwrap_or(0),
+                self.last_debug_messages_size.values().sum(),
+            )
+        };
+        let player_size =
+            data.get(&player_index).map_or(0, |storage| storage.size()) + player_messages_size;
+        let tick_size = Self::debug_data_size(data) + tick_messages_size;
+        let total_size = self.stored_debug_data_size
+            + Self::debug_data_size(&self.last_debug_data)
+            + Self::debug_data_size(&self.global_debug_data)
+            + self.debug_messages_size;
+        if player_size + data_size > limits.player_tick {
+            Some(DebugDataLimit::PlayerTick)
+        } else if tick_size + data_size > limits.tick {
+            Some(DebugDataLimit::Tick)
+        } else if total_size + data_size > limits.total {
+            Some(DebugDataLimit::Total)
+        } else {
+            None
+        }
     }
     fn handle_debug_command(
         &mut self,
@@ -212,6 +648,69 @@ impl<G: Game, T: RendererData<G>> HistorySharedState<G, T> {
         global: bool,
         command: DebugCommand<G>,
     ) {
+        match &command {
+            DebugCommand::Add { .. } => {
+                self.debug_layers.insert(DEFAULT_DEBUG_LAYER.to_owned());
+            }
+            DebugCommand::AddToLayer { layer, .. } => {
+                self.debug_layers.insert(layer.clone());
+            }
+            _ => {}
+        }
+        self.debug_players.insert(player_index);
+        let data_size = match &command {
+            DebugCommand::Add { data } | DebugCommand::AddToLayer { data, .. } => {
+                debug_data_size::<G>(data)
+            }
+            DebugCommand::Log { .. } | DebugCommand::Breakpoint { .. } => {
+                bincode::serialized_size(&command).unwrap()
+            }
+            _ => 0,
+        };
+        // Logs and breakpoints belong to the tick they were sent on, even if sent globally
+        let global = global
+            && !matches!(
+                command,
+                DebugCommand::Log { .. } | DebugCommand::Breakpoint { .. }
+            );
+        if data_size != 0 {
+            if let Some(limit) = self.exceeded_debug_data_limit(player_index, global, data_size) {
+                if self.dropped_debug_data.is_none() {
+                    warn!(
+                        "Debug data limit ({:?}) exceeded, dropping excess data",
+                        limit
+                    );
+                }
+                let count = self.dropped_debug_data.map_or(0, |dropped| dropped.count);
+                self.dropped_debug_data = Some(DroppedDebugData {
+                    limit,
+                    count: count + 1,
+                });
+                return;
+            }
+        }
+        match command {
+            DebugCommand::Log { level, message } => {
+                self.logs.push(DebugLogMessage {
+                    tick: self.len() - 1,
+                    player_index,
+                    level,
+                    message,
+                });
+                self.add_debug_message_size(player_index, data_size);
+                return;
+            }
+            DebugCommand::Breakpoint { label } => {
+                self.breakpoints.push(DebugBreakpoint {
+                    tick: self.len() - 1,
+                    player_index,
+                    label,
+                });
+                self.add_debug_message_size(player_index, data_size);
+                return;
+            }
+            _ => {}
+        }
         let data = if global {
             &mut self.global_debug_data
         } else {
@@ -221,45 +720,107 @@ impl<G: Game, T: RendererData<G>> HistorySharedState<G, T> {
             data.insert(player_index, DebugDataStorage::new());
         }
         let player_data = data.get_mut(&player_index).unwrap();
-        player_data.handle(command);
+        player_data.handle(command, data_size);
+        if !global {
+            self.last_debug_data_version += 1;
+        }
+    }
+    fn add_debug_message_size(&mut self, player_index: usize, size: u64) {
+        *self
+            .last_debug_messages_size
+            .entry(player_index)
+            .or_insert(0) += size;
+        self.debug_messages_size += size;
     }
     fn len(&self) -> usize {
         self.game.len()
     }
+    fn load_global_debug_data<R: BufRead>(&mut self, replay: &ReplayReader<G, R>) {
+        if let Some(data) = replay.global_debug_data() {
+            self.remember_debug_layers(data);
+            self.global_debug_data = DebugDataStorage::from_replay(data);
+        }
+    }
+    fn load_debug_data<R: BufRead>(&mut self, replay: &ReplayReader<G, R>) {
+        if let Some(data) = replay.debug_data() {
+            self.remember_debug_layers(data);
+            self.last_debug_data = DebugDataStorage::from_replay(data);
+            self.last_debug_data_version += 1;
+        }
+        for message in replay.debug_messages() {
+            match message {
+                ReplayDebugMessage::Log(message) => {
+                    self.debug_players.insert(message.player_index);
+                    self.logs.push(message.clone());
+                }
+                ReplayDebugMessage::Breakpoint(breakpoint) => {
+                    self.debug_players.insert(breakpoint.player_index);
+                    self.breakpoints.push(breakpoint.clone());
+                }
+            }
+        }
+    }
+    fn remember_debug_layers(&mut self, data: &PlayersDebugData<G>) {
+        for (&player_index, data) in data {
+            self.debug_players.insert(player_index);
+            for (layer, _) in data {
+                self.debug_layers.insert(layer.clone());
+            }
+        }
+    }
+    /// Whether any debug commands were received
+    fn has_debug_data(&self) -> bool {
+        !self.debug_players.is_empty()
+    }
 }
 
 pub struct History<G: Game, T: RendererData<G>> {
     shared_state: Arc<Mutex<HistorySharedState<G, T>>>,
     game: Window<HistorySnapshot<G>>,
     renderer_data: Window<HistorySnapshot<T>>,
+    game_cache: SnapshotCache<G>,
+    renderer_data_cache: SnapshotCache<T>,
+    /// Debug data given to the renderer, with the filter applied
     debug_data: Window<Arc<HashMap<usize, DebugDataStorage<G>>>>,
+    /// Debug data `debug_data` was made from, to only filter it again after a change
+    unfiltered_debug_data: Option<Window<Arc<HashMap<usize, DebugDataStorage<G>>>>>,
+    /// Filter `debug_data` was made with
+    applied_debug_data_filter: DebugDataFilter,
+    /// Copy of the debug data of the last tick and its version
+    last_debug_data: Option<(u64, Arc<HashMap<usize, DebugDataStorage<G>>>)>,
+    debug_data_filter: DebugDataFilter,
     debug_data_timer: Timer,
     prev_events: Arc<Vec<G::Event>>,
+    /// Breakpoints received so far, updated by `go_to`
+    breakpoints: Vec<DebugBreakpoint>,
     current_tick_time: f64,
 }
 
 impl<G: Game, T: RendererData<G>> History<G, T> {
-    pub fn new(initial_game_state: G) -> Self {
-        let shared_state = HistorySharedState::new(initial_game_state);
+    pub fn new(initial_game_state: G, options: &HistoryOptions) -> Self {
+        let shared_state = HistorySharedState::new(initial_game_state, options);
         let game = Window::new(&shared_state.game);
         let renderer_data = Window::new(&shared_state.renderer_data);
         let debug_data = Window {
             prev: None,
             current: Arc::new(shared_state.last_debug_data.clone()),
         };
-        let prev_events = shared_state
-            .events
-            .last()
-            .cloned()
-            .unwrap_or(Arc::new(Vec::new()));
+        let prev_events = Arc::new(Vec::new());
         let current_tick_time = (shared_state.len() - 1) as f64;
         Self {
             shared_state: Arc::new(Mutex::new(shared_state)),
             game,
             renderer_data,
+            game_cache: SnapshotCache::new(options.snapshot_cache_size),
+            renderer_data_cache: SnapshotCache::new(options.snapshot_cache_size),
             debug_data,
+            unfiltered_debug_data: None,
+            applied_debug_data_filter: default(),
+            last_debug_data: None,
+            debug_data_filter: default(),
             debug_data_timer: Timer::new(),
             prev_events,
+            breakpoints: Vec::new(),
             current_tick_time,
         }
     }
@@ -282,7 +843,9 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
                 }),
                 _ => None,
             },
-            global_debug_data: self.shared_state.lock().unwrap().global_debug_data.clone(),
+            global_debug_data: self
+                .debug_data_filter
+                .apply(&self.shared_state.lock().unwrap().global_debug_data),
             t: self.current_tick_time + 1.0 - self.current_tick_time.ceil(),
             prev_events: &self.prev_events,
         }
@@ -290,71 +853,163 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
     pub fn len(&self) -> usize {
         self.shared_state.lock().unwrap().len()
     }
+    /// Fails if history moved to disk could not be read, staying at some valid tick
     pub fn go_to(
         &mut self,
         tick_time: f64,
         collect_events: bool,
-    ) -> Box<dyn Iterator<Item = G::Event>> {
+    ) -> std::io::Result<Box<dyn Iterator<Item = G::Event>>> {
         let shared_state = self.shared_state.lock().unwrap();
         let tick_time = tick_time.min((shared_state.len() - 1) as f64);
         let tick = tick_time.ceil() as usize;
 
+        if shared_state.breakpoints.len() > self.breakpoints.len() {
+            self.breakpoints
+                .extend_from_slice(&shared_state.breakpoints[self.breakpoints.len()..]);
+        }
+
         let mut events = Vec::new();
         if collect_events && tick > self.game.current.tick {
             for tick in self.game.current.tick..tick {
-                events.push(shared_state.events[tick].clone());
+                events.push(shared_state.events.get(tick)?);
             }
         }
 
         if tick != self.game.current.tick {
             self.debug_data_timer = Timer::new();
         }
-        if let Some(data) = shared_state.debug_data.get(tick) {
-            self.debug_data = Window {
-                current: data.clone(),
+        let stored_debug_data = |tick: usize| shared_state.debug_data.get(tick);
+        let debug_data = if tick < shared_state.debug_data.len() {
+            Window {
+                current: stored_debug_data(tick)?,
                 prev: if tick > 0 {
-                    Some(shared_state.debug_data[tick - 1].clone())
+                    Some(stored_debug_data(tick - 1)?)
                 } else {
                     None
                 },
-            };
+            }
+        } else if tick > 0
+            && self.debug_data_timer.elapsed() < 0.5
+            && shared_state
+                .last_debug_data
+                .values()
+                .all(|debug_data| !debug_data.ready)
+        {
+            Window {
+                current: stored_debug_data(tick - 1)?,
+                prev: Some(stored_debug_data(tick - min(tick, 2))?),
+            }
         } else {
-            if tick > 0
-                && self.debug_data_timer.elapsed() < 0.5
-                && shared_state
-                    .last_debug_data
-                    .values()
-                    .all(|debug_data| !debug_data.ready)
-            {
-                self.debug_data = Window {
-                    current: shared_state.debug_data[tick - 1].clone(),
-                    prev: Some(shared_state.debug_data[tick - min(tick, 2)].clone()),
-                };
+            Window {
+                current: Self::last_debug_data(&mut self.last_debug_data, &shared_state),
+                prev: if tick > 0 {
+                    Some(stored_debug_data(tick - 1)?)
+                } else {
+                    None
+                },
+            }
+        };
+        let unchanged = self.applied_debug_data_filter == self.debug_data_filter
+            && self
+                .unfiltered_debug_data
+                .as_ref()
+                .map_or(false, |unfiltered| unfiltered.ptr_eq(&debug_data));
+        if !unchanged {
+            let filter = &self.debug_data_filter;
+            self.debug_data = if filter.is_empty() {
+                debug_data.clone()
             } else {
-                self.debug_data = Window {
-                    current: Arc::new(shared_state.last_debug_data.clone()),
-                    prev: if tick > 0 {
-                        Some(shared_state.debug_data[tick - 1].clone())
-                    } else {
-                        None
-                    },
+                Window {
+                    current: Arc::new(filter.apply(&debug_data.current)),
+                    prev: debug_data
+                        .prev
+                        .as_ref()
+                        .map(|prev| Arc::new(filter.apply(prev))),
                 }
-            }
+            };
+            self.unfiltered_debug_data = Some(debug_data);
+            self.applied_debug_data_filter = filter.clone();
         }
 
-        self.game.go_to(tick, &shared_state.game);
-        self.renderer_data.go_to(tick, &shared_state.renderer_data);
+        self.game
+            .go_to(tick, &shared_state.game, &mut self.game_cache)?;
+        self.renderer_data.go_to(
+            tick,
+            &shared_state.renderer_data,
+            &mut self.renderer_data_cache,
+        )?;
         self.prev_events = if tick > 0 {
-            shared_state.events[tick - 1].clone()
+            shared_state.events.get(tick - 1)?
         } else {
             Arc::new(Vec::new())
         };
         self.current_tick_time = tick_time;
-        Box::new(
-            events
-                .into_iter()
-                .flat_map(move |events| (0..events.len()).map(move |i| events[i].clone())),
-        )
+        Ok(Box::new(events.into_iter().flat_map(move |events| {
+            (0..events.len()).map(move |i| events[i].clone())
+        })))
+    }
+    /// Copy of `last_debug_data` of the shared state, made again only after it changes
+    fn last_debug_data(
+        cached: &mut Option<(u64, Arc<HashMap<usize, DebugDataStorage<G>>>)>,
+        shared_state: &HistorySharedState<G, T>,
+    ) -> Arc<HashMap<usize, DebugDataStorage<G>>> {
+        let version = shared_state.last_debug_data_version;
+        match cached {
+            Some((cached_version, data)) if *cached_version == version => data.clone(),
+            _ => {
+                let data = Arc::new(shared_state.last_debug_data.clone());
+                *cached = Some((version, data.clone()));
+                data
+            }
+        }
+    }
+    /// Only debug data passing the filter is given to the renderer
+    pub fn set_debug_data_filter(&mut self, filter: DebugDataFilter) {
+        self.debug_data_filter = filter;
+    }
+    pub fn debug_data_filter(&self) -> &DebugDataFilter {
+        &self.debug_data_filter
+    }
+    /// Players that have sent any debug data
+    pub fn debug_players(&self) -> Vec<usize> {
+        let shared_state = self.shared_state.lock().unwrap();
+        shared_state.debug_players.iter().copied().collect()
+    }
+    /// Names of all layers debug data was sent to
+    pub fn debug_layers(&self) -> Vec<String> {
+        let shared_state = self.shared_state.lock().unwrap();
+        shared_state.debug_layers.iter().cloned().collect()
+    }
+    /// Debug data that was dropped because of the limits since last dismissal, if any
+    pub fn dropped_debug_data(&self) -> Option<DroppedDebugData> {
+        self.shared_state.lock().unwrap().dropped_debug_data
+    }
+    /// Forget about dropped debug data, so that only data dropped later is reported
+    pub fn dismiss_dropped_debug_data(&mut self) {
+        self.shared_state.lock().unwrap().dropped_debug_data = None;
+    }
+    /// Up to `count` last log messages passing the filter sent not later than current tick,
+    /// skipping `offset` latest ones
+    pub fn logs(&self, offset: usize, count: usize) -> Vec<DebugLogMessage> {
+        let shared_state = self.shared_state.lock().unwrap();
+        let current_tick = self.game.current.tick;
+        let end = shared_state
+            .logs
+            .partition_point(|message| message.tick <= current_tick);
+        let mut logs: Vec<DebugLogMessage> = shared_state.logs[..end]
+            .iter()
+            .rev()
+            .filter(|message| self.debug_data_filter.shows_log(message))
+            .skip(offset)
+            .take(count)
+            .cloned()
+            .collect();
+        logs.reverse();
+        logs
+    }
+    /// Breakpoints received before last `go_to`, ordered by tick
+    pub fn breakpoints(&self) -> &[DebugBreakpoint] {
+        &self.breakpoints
     }
     pub fn tick_handler(&self) -> impl FnMut(&G, Vec<G::Event>) + Send + 'static {
         let shared_state = self.shared_state.clone();
@@ -362,13 +1017,15 @@ impl<G: Game, T: RendererData<G>> History<G, T> {
             shared_state.lock().unwrap().push(game.clone(), events);
         }
     }
-    pub fn debug_command_handler(&self) -> impl Fn(usize, bool, DebugCommand<G>) + Send + 'static {
+    pub fn debug_command_handler(
+        &self,
+    ) -> impl Fn(usize, bool, Vec<DebugCommand<G>>) + Send + Sync + 'static {
         let shared_state = self.shared_state.clone();
-        move |player_index, global, command| {
-            shared_state
-                .lock()
-                .unwrap()
-                .handle_debug_command(player_index, global, command);
+        move |player_index, global, commands| {
+            let mut shared_state = shared_state.lock().unwrap();
+            for command in commands {
+                shared_state.handle_debug_command(player_index, global, command);
+            }
         }
     }
 }
@@ -1,22 +1,66 @@
 use super::*;
 
 impl<G: Game, T: RendererData<G>> History<G, T> {
+    /// Save replay, including debug data, log messages and breakpoints if any were received
     pub fn save(&self, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
         let shared_state = self.shared_state.lock().unwrap();
-        let mut entries = shared_state.game.entries.iter();
-        let mut current_state = match entries.next().unwrap() {
+        let with_debug_data = shared_state.has_debug_data();
+        let debug_data = |tick: usize| -> std::io::Result<PlayersDebugData<G>> {
+            Ok(if tick < shared_state.debug_data.len() {
+                DebugDataStorage::to_replay(&shared_state.debug_data.get(tick)?)
+            } else {
+                DebugDataStorage::to_replay(&shared_state.last_debug_data)
+            })
+        };
+        let debug_messages = |tick: usize| -> Vec<ReplayDebugMessage> {
+            let logs = &shared_state.logs;
+            let logs = &logs[logs.partition_point(|message| message.tick < tick)
+                ..logs.partition_point(|message| message.tick <= tick)];
+            let breakpoints = &shared_state.breakpoints;
+            let breakpoints = &breakpoints[breakpoints
+                .partition_point(|breakpoint| breakpoint.tick < tick)
+                ..breakpoints.partition_point(|breakpoint| breakpoint.tick <= tick)];
+            logs.iter()
+                .cloned()
+                .map(ReplayDebugMessage::Log)
+                .chain(
+                    breakpoints
+                        .iter()
+                        .cloned()
+                        .map(ReplayDebugMessage::Breakpoint),
+                )
+                .collect()
+        };
+        let entries = &shared_state.game.entries;
+        let mut current_state = match &*entries.get(0)? {
             DiffEntry::Value(state) => state.clone(),
             DiffEntry::Delta(_) => panic!("First entry must be value, not diff"),
         };
-        current_state.write_to(writer)?;
-        for (entry, events) in entries.zip(shared_state.events.iter()) {
+        if with_debug_data {
+            write_replay_start_with_debug_data::<G>(
+                writer,
+                &current_state,
+                &DebugDataStorage::to_replay(&shared_state.global_debug_data),
+            )?;
+            write_replay_debug_data::<G>(writer, &debug_data(0)?, &debug_messages(0))?;
+        } else {
+            write_replay_start(writer, &current_state)?;
+        }
+        for tick in 1..entries.len() {
             let prev_state = current_state.clone();
-            match entry {
+            match &*entries.get(tick)? {
                 DiffEntry::Value(state) => current_state = state.clone(),
                 DiffEntry::Delta(delta) => current_state.update(delta),
             };
-            events.write_to(writer)?;
-            prev_state.diff(&current_state).write_to(writer)?;
+            write_replay_tick(
+                writer,
+                &shared_state.events.get(tick - 1)?,
+                &prev_state,
+                &current_state,
+            )?;
+            if with_debug_data {
+                write_replay_debug_data::<G>(writer, &debug_data(tick)?, &debug_messages(tick))?;
+            }
         }
         Ok(())
     }
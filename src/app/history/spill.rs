@@ -0,0 +1,163 @@
+use super::*;
+
+use std::cell::RefCell;
+use std::io::{Seek, SeekFrom};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Temporary file that older history entries are moved to, removed when dropped
+pub(super) struct SpillFile {
+    path: std::path::PathBuf,
+    file: std::fs::File,
+}
+
+impl SpillFile {
+    pub fn create() -> std::io::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "codegame-history-{}-{}.tmp",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        ));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Self { path, file })
+    }
+    /// Append data to the end of the file, returning its offset
+    fn write(&self, data: &[u8]) -> std::io::Result<u64> {
+        let mut file = &self.file;
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(data)?;
+        Ok(offset)
+    }
+    fn read(&self, offset: u64, size: u64) -> std::io::Result<Vec<u8>> {
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; size as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!("Failed to remove {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Number of spilled entries kept after reading them, so that seeking around does not hit the disk
+const PAGE_CACHE_SIZE: usize = 16;
+
+enum Spillable<T> {
+    InMemory { value: Arc<T>, size: u64 },
+    Spilled { offset: u64, size: u64 },
+}
+
+/// Append-only list that can move its oldest entries to a `SpillFile`,
+/// reading them back when accessed
+pub(super) struct SpillVec<T> {
+    entries: Vec<Spillable<T>>,
+    /// Entries before this one are spilled
+    first_in_memory: usize,
+    memory_size: u64,
+    file: Option<Arc<SpillFile>>,
+    /// Spilled entries read recently, most recently used at the back
+    page_cache: RefCell<VecDeque<(usize, Arc<T>)>>,
+}
+
+impl<T: Serialize + for<'de> Deserialize<'de>> SpillVec<T> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            first_in_memory: 0,
+            memory_size: 0,
+            file: None,
+            page_cache: RefCell::new(VecDeque::new()),
+        }
+    }
+    pub fn set_file(&mut self, file: Option<Arc<SpillFile>>) {
+        self.file = file;
+    }
+    pub fn push(&mut self, value: T) {
+        let size = bincode::serialized_size(&value).unwrap();
+        self.memory_size += size;
+        self.entries.push(Spillable::InMemory {
+            value: Arc::new(value),
+            size,
+        });
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn get(&self, index: usize) -> std::io::Result<Arc<T>> {
+        let (offset, size) = match &self.entries[index] {
+            Spillable::InMemory { value, .. } => return Ok(value.clone()),
+            &Spillable::Spilled { offset, size } => (offset, size),
+        };
+        let mut page_cache = self.page_cache.borrow_mut();
+        if let Some(position) = page_cache
+            .iter()
+            .position(|&(cached_index, _)| cached_index == index)
+        {
+            let cached = page_cache.remove(position).unwrap();
+            let value = cached.1.clone();
+            page_cache.push_back(cached);
+            return Ok(value);
+        }
+        // Entries are only spilled when there is a file
+        let file = self.file.as_ref().unwrap();
+        let data = file.read(offset, size)?;
+        let value: Arc<T> = Arc::new(
+            bincode::deserialize(&data)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+        );
+        page_cache.push_back((index, value.clone()));
+        if page_cache.len() > PAGE_CACHE_SIZE {
+            page_cache.pop_front();
+        }
+        Ok(value)
+    }
+    pub fn last(&self) -> Option<std::io::Result<Arc<T>>> {
+        match self.len() {
+            0 => None,
+            len => Some(self.get(len - 1)),
+        }
+    }
+    /// Serialized size of entries kept in memory
+    pub fn memory_size(&self) -> u64 {
+        self.memory_size
+    }
+    /// Move the oldest entry still in memory to the file, returns false if nothing could be spilled
+    pub fn spill_oldest(&mut self) -> bool {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return false,
+        };
+        let entry = match self.entries.get_mut(self.first_in_memory) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let (data, size) = match entry {
+            Spillable::InMemory { value, size } => {
+                (bincode::serialize(value.as_ref()).unwrap(), *size)
+            }
+            Spillable::Spilled { .. } => unreachable!(),
+        };
+        match file.write(&data) {
+            Ok(offset) => {
+                *entry = Spillable::Spilled { offset, size };
+                self.first_in_memory += 1;
+                self.memory_size -= size;
+                true
+            }
+            Err(e) => {
+                error!("Failed to spill history to disk: {}", e);
+                false
+            }
+        }
+    }
+}
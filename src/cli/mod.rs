@@ -0,0 +1,284 @@
+use super::*;
+
+use std::path::PathBuf;
+
+/// Command line interface shared by all games
+#[derive(Clap)]
+pub struct CliOptions {
+    #[clap(subcommand)]
+    pub command: CliCommand,
+}
+
+#[derive(Clap)]
+pub enum CliCommand {
+    /// Open the app to configure and watch a game
+    #[cfg(feature = "rendering")]
+    Play {
+        /// Start game from this config instead of showing config screen
+        #[clap(long)]
+        config: Option<PathBuf>,
+        /// Override config values, like `seed=42` or `players[1]=tcp:31005`
+        #[clap(long = "set")]
+        overrides: Vec<OptionsOverride>,
+    },
+    /// Run a game without rendering
+    Run {
+        /// Path to FullOptions json
+        #[clap(long)]
+        config: PathBuf,
+        /// Override config values, like `seed=42` or `players[1]=tcp:31005`
+        #[clap(long = "set")]
+        overrides: Vec<OptionsOverride>,
+        /// Where to save the replay
+        #[clap(long)]
+        save_replay: Option<PathBuf>,
+        /// Where to save the results, merged config is saved next to them
+        #[clap(long)]
+        save_results: Option<PathBuf>,
+        /// Where to save players' log messages, as json lines
+        #[clap(long)]
+        save_logs: Option<PathBuf>,
+        /// Make debug interface available to players, saving their debug commands next to the replay
+        #[clap(long)]
+        save_debug: bool,
+    },
+    /// Watch a saved replay
+    #[cfg(feature = "rendering")]
+    Replay {
+        /// Path to the replay
+        path: PathBuf,
+    },
+    /// Repeat a saved replay with new players
+    Repeat {
+        /// Path to the replay
+        path: PathBuf,
+        /// Path to FullOptions json to take players from
+        #[clap(long)]
+        config: PathBuf,
+        /// Override config values, like `players[1]=tcp:31005`
+        #[clap(long = "set")]
+        overrides: Vec<OptionsOverride>,
+        /// Where to save the results, merged config is saved next to them
+        #[clap(long)]
+        save_results: Option<PathBuf>,
+    },
+    /// Write JSON schemas of config and results files
+    Schema {
+        /// Where to put schema files
+        #[clap(long)]
+        target_dir: PathBuf,
+    },
+    /// Generate clients for all languages
+    #[cfg(feature = "client-gen")]
+    ClientGen {
+        /// Name of the game
+        #[clap(long)]
+        name: String,
+        /// Version of the game
+        #[clap(long)]
+        version: String,
+        /// Where to put generated clients
+        #[clap(long)]
+        target_dir: PathBuf,
+        /// Only generate these languages
+        #[clap(long)]
+        language: Vec<String>,
+    },
+}
+
+fn load_full_options<G: Game>(
+    path: &std::path::Path,
+    overrides: &[OptionsOverride],
+) -> anyhow::Result<FullOptions<G>> {
+    FullOptions::load_with_overrides(
+        std::io::BufReader::new(std::fs::File::open(path).context("Failed to open config")?),
+        overrides,
+    )
+}
+
+fn save_results_handler<G: Game>(
+    path: PathBuf,
+    full_options: &FullOptions<G>,
+) -> anyhow::Result<Box<dyn FnOnce(FullResults<G>) + Send>> {
+    full_options.save(std::io::BufWriter::new(
+        std::fs::File::create(path.with_extension("config.json"))
+            .context("Failed to create merged config file")?,
+    ))?;
+    let writer = std::io::BufWriter::new(
+        std::fs::File::create(path).context("Failed to create results file")?,
+    );
+    Ok(Box::new(move |results| {
+        serde_json::to_writer_pretty(writer, &results).expect("Failed to write results");
+    }))
+}
+
+/// Run commands that don't need rendering.
+/// Commands that need rendering fail, use `run_cli_with_renderer` for them
+pub fn run_cli<G: Game>(options: CliOptions) -> anyhow::Result<()> {
+    match options.command {
+        CliCommand::Run {
+            config,
+            overrides,
+            save_replay,
+            save_results,
+            save_logs,
+            save_debug,
+        } => {
+            let full_options = load_full_options(&config, &overrides)?;
+            let debug_interface = if save_debug || save_logs.is_some() {
+                Some(headless_debug_interface::<G>(
+                    G::default_debug_state()
+                        .context("Game does not support debugging without the app")?,
+                ))
+            } else {
+                None
+            };
+            let debug_path = match (&save_replay, save_debug) {
+                (Some(path), true) => Some(path.with_extension("debug.jsonl")),
+                (None, true) => anyhow::bail!("Saving debug commands requires saving the replay"),
+                (_, false) => None,
+            };
+            let results_handler = match save_results {
+                Some(path) => Some(save_results_handler(path, &full_options)?),
+                None => None,
+            };
+            let mut processor = GameProcessor::<G>::new_full(full_options)?;
+            if let Some(path) = save_replay {
+                processor.set_tick_handler(save_replay_tick_handler::<G, _>(
+                    std::io::BufWriter::new(
+                        std::fs::File::create(path).context("Failed to create replay file")?,
+                    ),
+                ));
+            }
+            if let Some(handler) = results_handler {
+                processor.set_results_handler(handler);
+            }
+            if let Some(path) = save_logs {
+                processor.add_observer(Box::new(DebugLogWriter::new(std::io::BufWriter::new(
+                    std::fs::File::create(path).context("Failed to create log file")?,
+                ))));
+            }
+            if let Some(path) = debug_path {
+                processor.add_observer(Box::new(DebugCommandWriter::new(std::io::BufWriter::new(
+                    std::fs::File::create(path).context("Failed to create debug file")?,
+                ))));
+            }
+            processor.run(debug_interface.as_ref());
+        }
+        CliCommand::Repeat {
+            path,
+            config,
+            overrides,
+            save_results,
+        } => {
+            let full_options = load_full_options(&config, &overrides)?;
+            let results_handler = match save_results {
+                Some(path) => Some(save_results_handler(path, &full_options)?),
+                None => None,
+            };
+            let mut processor = GameProcessor::<G>::repeat_full(
+                full_options,
+                std::fs::File::open(path).context("Failed to open replay")?,
+            )?;
+            if let Some(handler) = results_handler {
+                processor.set_results_handler(handler);
+            }
+            processor.run(None);
+        }
+        CliCommand::Schema { target_dir } => {
+            std::fs::create_dir_all(&target_dir)?;
+            let write = |name: &str, schema: serde_json::Value| -> anyhow::Result<()> {
+                serde_json::to_writer_pretty(
+                    std::io::BufWriter::new(std::fs::File::create(
+                        target_dir.join(format!("{}.schema.json", name)),
+                    )?),
+                    &schema,
+                )?;
+                Ok(())
+            };
+            write(
+                "full-options",
+                json_schema_document::<FullOptions<G>>("FullOptions"),
+            )?;
+            write(
+                "full-results",
+                json_schema_document::<FullResults<G>>("FullResults"),
+            )?;
+            write(
+                "game-init-config",
+                json_schema_document::<GameInitConfig<G>>("GameInitConfig"),
+            )?;
+            write(
+                "tcp-player-options",
+                json_schema_document::<TcpPlayerOptions>("TcpPlayerOptions"),
+            )?;
+        }
+        #[cfg(feature = "client-gen")]
+        CliCommand::ClientGen {
+            name,
+            version,
+            target_dir,
+            language,
+        } => {
+            client_gen::gen_all::<G>(
+                &client_gen::Options {
+                    name: &name,
+                    target_dir: &target_dir,
+                    version: &version,
+                },
+                if language.is_empty() {
+                    None
+                } else {
+                    Some(language.iter().map(|language| language.as_str()).collect())
+                },
+                &HashMap::new(),
+            )?;
+        }
+        #[cfg(feature = "rendering")]
+        CliCommand::Play { .. } | CliCommand::Replay { .. } => {
+            anyhow::bail!("This command needs a renderer");
+        }
+    }
+    Ok(())
+}
+
+/// Run any command, opening the app for the ones that need rendering
+#[cfg(feature = "rendering")]
+pub fn run_cli_with_renderer<G: Game, R: Renderer<G>>(
+    options: CliOptions,
+    geng: &Rc<Geng>,
+    renderer: R,
+    preferences: Rc<RefCell<AutoSave<AppPreferences<R::Preferences>>>>,
+    config_screen: impl FnOnce(
+        R,
+        Rc<RefCell<AutoSave<AppPreferences<R::Preferences>>>>,
+    ) -> ConfigScreen<G, R>,
+) -> anyhow::Result<()> {
+    match options.command {
+        CliCommand::Play { config: None, .. } => {
+            geng::run(geng, config_screen(renderer, preferences));
+        }
+        CliCommand::Play {
+            config: Some(path),
+            overrides,
+        } => {
+            let processor = GameProcessor::<G>::new_full(load_full_options(&path, &overrides)?)?;
+            geng::run(
+                geng,
+                GameScreen::new(geng, processor, renderer, preferences),
+            );
+        }
+        CliCommand::Replay { path } => {
+            let history = futures::executor::block_on(History::load(
+                path.to_str().context("Replay path is not valid unicode")?,
+                preferences.borrow().history_options(),
+            ));
+            geng::run(
+                geng,
+                GameScreen::replay(geng, history, renderer, preferences),
+            );
+        }
+        command => run_cli::<G>(CliOptions { command })?,
+    }
+    Ok(())
+}
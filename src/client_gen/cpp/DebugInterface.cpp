@@ -1,6 +1,15 @@
 #include "DebugInterface.hpp"
 #include "model/ClientMessage.hpp"
 
+void DebugCommandBuffer::writeBytes(const char* buffer, size_t byteCount)
+{
+    data.insert(data.end(), buffer, buffer + byteCount);
+}
+
+void DebugCommandBuffer::flush()
+{
+}
+
 DebugInterface::DebugInterface(const std::shared_ptr<InputStream>& inputStream, const std::shared_ptr<OutputStream>& outputStream)
     : inputStream(inputStream)
     , outputStream(outputStream)
@@ -9,15 +18,43 @@ DebugInterface::DebugInterface(const std::shared_ptr<InputStream>& inputStream,
 
 void DebugInterface::send(const DebugCommand& command)
 {
+    command.writeTo(buffer);
+    buffer.commandCount++;
+}
+
+void DebugInterface::breakpoint(const std::string& label)
+{
+    send(DebugCommand::Breakpoint(label));
+}
+
+void DebugInterface::flush()
+{
+    if (buffer.commandCount == 0) {
+        return;
+    }
     // TODO: Construct actual message, this is a hack :)
-    outputStream->write(ClientMessage::DebugMessage::TAG);
-    command.writeTo(*outputStream);
+    outputStream->write(ClientMessage::DebugMessages::TAG);
+    outputStream->write(buffer.commandCount);
+    outputStream->writeBytes(buffer.data.data(), buffer.data.size());
     outputStream->flush();
+    buffer.data.clear();
+    buffer.commandCount = 0;
 }
 
 DebugState DebugInterface::getState()
 {
+    flush();
     ClientMessage::RequestDebugState().writeTo(*outputStream);
     outputStream->flush();
     return DebugState::readFrom(*inputStream);
+}
+
+DebugQueryResponse DebugInterface::query(const DebugQuery& query)
+{
+    flush();
+    // TODO: Construct actual message, this is a hack :)
+    outputStream->write(ClientMessage::RequestDebugQuery::TAG);
+    query.writeTo(*outputStream);
+    outputStream->flush();
+    return DebugQueryResponse::readFrom(*inputStream);
 }
\ No newline at end of file
@@ -3,18 +3,34 @@
 
 #include "Stream.hpp"
 #include "model/DebugCommand.hpp"
+#include "model/DebugQuery.hpp"
+#include "model/DebugQueryResponse.hpp"
 #include "model/DebugState.hpp"
 #include <memory>
+#include <string>
+#include <vector>
+
+class DebugCommandBuffer : public OutputStream {
+public:
+    void writeBytes(const char* buffer, size_t byteCount);
+    void flush();
+    std::vector<char> data;
+    int commandCount = 0;
+};
 
 class DebugInterface {
 public:
     DebugInterface(const std::shared_ptr<InputStream>& inputStream, const std::shared_ptr<OutputStream>& outputStream);
     void send(const DebugCommand& command);
+    void breakpoint(const std::string& label);
+    void flush();
     DebugState getState();
+    DebugQueryResponse query(const DebugQuery& query);
 
 private:
     std::shared_ptr<InputStream> inputStream;
     std::shared_ptr<OutputStream> outputStream;
+    DebugCommandBuffer buffer;
 };
 
 #endif
\ No newline at end of file
@@ -22,12 +22,15 @@ public:
         while (true) {
             auto message = ServerMessage::readFrom(*inputStream);
             if (auto getActionMessage = std::dynamic_pointer_cast<ServerMessage::GetAction>(message)) {
-                ClientMessage::ActionMessage(myStrategy.getAction(getActionMessage->playerView, getActionMessage->debugAvailable ? &debugInterface : nullptr)).writeTo(*outputStream);
+                ClientMessage::ActionMessage actionMessage(myStrategy.getAction(getActionMessage->playerView, getActionMessage->debugAvailable ? &debugInterface : nullptr));
+                debugInterface.flush();
+                actionMessage.writeTo(*outputStream);
                 outputStream->flush();
             } else if (auto finishMessage = std::dynamic_pointer_cast<ServerMessage::Finish>(message)) {
                 break;
             } else if (auto debugUpdateMessage = std::dynamic_pointer_cast<ServerMessage::DebugUpdate>(message)) {
                 myStrategy.debugUpdate(debugUpdateMessage->playerView, debugInterface);
+                debugInterface.flush();
                 ClientMessage::DebugUpdateDone().writeTo(*outputStream);
                 outputStream->flush();
             }
@@ -1,3 +1,4 @@
+using System.Collections.Generic;
 using System.IO;
 
 namespace ProjectName
@@ -6,6 +7,7 @@ namespace ProjectName
     {
         private BinaryWriter writer;
         private BinaryReader reader;
+        private List<Model.DebugCommand> commands = new List<Model.DebugCommand>();
         public DebugInterface(BinaryReader reader, BinaryWriter writer)
         {
             this.reader = reader;
@@ -13,14 +15,35 @@ namespace ProjectName
         }
         public void Send(Model.DebugCommand command)
         {
-            new Model.ClientMessage.DebugMessage(command).WriteTo(writer);
+            commands.Add(command);
+        }
+        public void Breakpoint(string label)
+        {
+            Send(new Model.DebugCommand.Breakpoint(label));
+        }
+        public void Flush()
+        {
+            if (commands.Count == 0)
+            {
+                return;
+            }
+            new Model.ClientMessage.DebugMessages(commands.ToArray()).WriteTo(writer);
             writer.Flush();
+            commands.Clear();
         }
         public Model.DebugState GetState()
         {
+            Flush();
             new Model.ClientMessage.RequestDebugState().WriteTo(writer);
             writer.Flush();
             return Model.DebugState.ReadFrom(reader);
         }
+        public Model.DebugQueryResponse Query(Model.DebugQuery query)
+        {
+            Flush();
+            new Model.ClientMessage.RequestDebugQuery(query).WriteTo(writer);
+            writer.Flush();
+            return Model.DebugQueryResponse.ReadFrom(reader);
+        }
     }
 }
\ No newline at end of file
@@ -32,7 +32,9 @@ namespace ProjectName
                 switch (Model.ServerMessage.ReadFrom(reader))
                 {
                     case Model.ServerMessage.GetAction message:
-                        new Model.ClientMessage.ActionMessage(myStrategy.GetAction(message.PlayerView, message.DebugAvailable ? debugInterface : null)).WriteTo(writer);
+                        var action = myStrategy.GetAction(message.PlayerView, message.DebugAvailable ? debugInterface : null);
+                        debugInterface.Flush();
+                        new Model.ClientMessage.ActionMessage(action).WriteTo(writer);
                         writer.Flush();
                         break;
                     case Model.ServerMessage.Finish message:
@@ -40,6 +42,7 @@ namespace ProjectName
                         break;
                     case Model.ServerMessage.DebugUpdate message:
                         myStrategy.DebugUpdate(message.PlayerView, debugInterface);
+                        debugInterface.Flush();
                         new Model.ClientMessage.DebugUpdateDone().WriteTo(writer);
                         writer.Flush();
                         break;
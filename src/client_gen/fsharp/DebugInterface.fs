@@ -1,11 +1,27 @@
 namespace ProjectName
 
 type DebugInterface(reader, writer) =
-    member this.send(command) =
-        (Model.ClientMessage.DebugMessage { Command = command }).writeTo writer
-        writer.Flush()
+    let commands = System.Collections.Generic.List<Model.DebugCommand>()
+
+    member this.send(command) = commands.Add command
+
+    member this.breakpoint(label) =
+        this.send (Model.DebugCommand.Breakpoint { Label = label })
+
+    member this.flush() =
+        if commands.Count > 0 then
+            (Model.ClientMessage.DebugMessages { Commands = commands.ToArray() }).writeTo writer
+            writer.Flush()
+            commands.Clear()
 
     member this.getState(): Model.DebugState =
+        this.flush ()
         (new Model.ClientMessageRequestDebugState()).writeTo writer
         writer.Flush()
         Model.DebugState.readFrom reader
+
+    member this.query(query): Model.DebugQueryResponse =
+        this.flush ()
+        (Model.ClientMessage.RequestDebugQuery { Query = query }).writeTo writer
+        writer.Flush()
+        Model.DebugQueryResponse.readFrom reader
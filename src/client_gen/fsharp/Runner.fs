@@ -25,16 +25,17 @@ module Runner =
             let rec loop () =
                 match Model.ServerMessage.readFrom reader with
                 | Model.ServerMessage.GetAction message ->
-                    (Model.ClientMessage.ActionMessage
-                        { Action =
-                              myStrategy.getAction
-                                  (message.PlayerView, (if message.DebugAvailable then Some debugInterface else None)) }).writeTo
-                        writer
+                    let action =
+                        myStrategy.getAction
+                            (message.PlayerView, (if message.DebugAvailable then Some debugInterface else None))
+                    debugInterface.flush ()
+                    (Model.ClientMessage.ActionMessage { Action = action }).writeTo writer
                     writer.Flush()
                     loop ()
                 | Model.ServerMessage.Finish message -> ()
                 | Model.ServerMessage.DebugUpdate message ->
                     myStrategy.debugUpdate (message.PlayerView, debugInterface)
+                    debugInterface.flush ()
                     (new Model.ClientMessageDebugUpdateDone()).writeTo writer
                     writer.Flush()
                     loop ()
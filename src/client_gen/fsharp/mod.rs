@@ -11,6 +11,7 @@ impl<G: Game> ClientGen<G> for trans_gen::GeneratorImpl<Generator> {
         gen.add(&trans::Schema::of::<ClientMessage<G>>());
         gen.add(&trans::Schema::of::<ServerMessage<G>>());
         gen.add(&trans::Schema::of::<G::DebugState>());
+        gen.add(&trans::Schema::of::<G::DebugQueryResponse>());
         let result = gen.result();
         result.write_to(options.target_dir)?;
         write_file(
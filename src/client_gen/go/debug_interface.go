@@ -6,21 +6,45 @@ import (
 )
 
 type DebugInterface struct {
-	Reader *bufio.Reader
-	Writer *bufio.Writer
+	Reader   *bufio.Reader
+	Writer   *bufio.Writer
+	commands *[]DebugCommand
+}
+
+func NewDebugInterface(reader *bufio.Reader, writer *bufio.Writer) DebugInterface {
+	return DebugInterface{
+		Reader:   reader,
+		Writer:   writer,
+		commands: &[]DebugCommand{},
+	}
 }
 
 func (debugInterface DebugInterface) Send(command DebugCommand) {
-	ClientMessageDebugMessage{
-		Command: command,
+	*debugInterface.commands = append(*debugInterface.commands, command)
+}
+
+func (debugInterface DebugInterface) Breakpoint(label string) {
+	debugInterface.Send(DebugCommandBreakpoint{
+		Label: label,
+	})
+}
+
+func (debugInterface DebugInterface) Flush() {
+	if len(*debugInterface.commands) == 0 {
+		return
+	}
+	ClientMessageDebugMessages{
+		Commands: *debugInterface.commands,
 	}.Write(debugInterface.Writer)
 	err := debugInterface.Writer.Flush()
 	if err != nil {
 		panic(err)
 	}
+	*debugInterface.commands = nil
 }
 
 func (debugInterface DebugInterface) GetState() DebugState {
+	debugInterface.Flush()
 	ClientMessageRequestDebugState{}.Write(debugInterface.Writer)
 	err := debugInterface.Writer.Flush()
 	if err != nil {
@@ -28,3 +52,15 @@ func (debugInterface DebugInterface) GetState() DebugState {
 	}
 	return ReadDebugState(debugInterface.Reader)
 }
+
+func (debugInterface DebugInterface) Query(query DebugQuery) DebugQueryResponse {
+	debugInterface.Flush()
+	ClientMessageRequestDebugQuery{
+		Query: query,
+	}.Write(debugInterface.Writer)
+	err := debugInterface.Writer.Flush()
+	if err != nil {
+		panic(err)
+	}
+	return ReadDebugQueryResponse(debugInterface.Reader)
+}
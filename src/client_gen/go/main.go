@@ -35,10 +35,7 @@ func NewRunner(host string, port uint16, token string) Runner {
 
 func (runner Runner) Run() {
 	myStrategy := NewMyStrategy()
-	debugInterface := DebugInterface{
-		Reader: runner.reader,
-		Writer: runner.writer,
-	}
+	debugInterface := NewDebugInterface(runner.reader, runner.writer)
 loop:
 	for {
 		switch message := ReadServerMessage(runner.reader).(type) {
@@ -49,6 +46,7 @@ loop:
 			} else {
 				action = myStrategy.getAction(message.PlayerView, nil)
 			}
+			debugInterface.Flush()
 			ClientMessageActionMessage{
 				Action: action,
 			}.Write(runner.writer)
@@ -60,6 +58,7 @@ loop:
 			break loop
 		case ServerMessageDebugUpdate:
 			myStrategy.debugUpdate(message.PlayerView, debugInterface)
+			debugInterface.Flush()
 			ClientMessageDebugUpdateDone{}.Write(runner.writer)
 			err := runner.writer.Flush()
 			if err != nil {
@@ -1,10 +1,13 @@
 import java.io.IOException;
 import java.io.InputStream;
 import java.io.OutputStream;
+import java.util.ArrayList;
+import java.util.List;
 
 public class DebugInterface {
     private InputStream inputStream;
     private OutputStream outputStream;
+    private List<model.DebugCommand> commands = new ArrayList<>();
 
     public DebugInterface(InputStream inputStream, OutputStream outputStream) {
         this.inputStream = inputStream;
@@ -12,15 +15,28 @@ public class DebugInterface {
     }
 
     public void send(model.DebugCommand command) {
+        commands.add(command);
+    }
+
+    public void breakpoint(String label) {
+        send(new model.DebugCommand.Breakpoint(label));
+    }
+
+    public void flush() {
+        if (commands.isEmpty()) {
+            return;
+        }
         try {
-            new model.ClientMessage.DebugMessage(command).writeTo(outputStream);
+            new model.ClientMessage.DebugMessages(commands.toArray(new model.DebugCommand[0])).writeTo(outputStream);
             outputStream.flush();
         } catch (IOException e) {
             throw new RuntimeException(e);
         }
+        commands.clear();
     }
 
     public model.DebugState getState() {
+        flush();
         try {
             new model.ClientMessage.RequestDebugState().writeTo(outputStream);
             outputStream.flush();
@@ -29,4 +45,15 @@ public class DebugInterface {
             throw new RuntimeException(e);
         }
     }
+
+    public model.DebugQueryResponse query(model.DebugQuery query) {
+        flush();
+        try {
+            new model.ClientMessage.RequestDebugQuery(query).writeTo(outputStream);
+            outputStream.flush();
+            return model.DebugQueryResponse.readFrom(inputStream);
+        } catch (IOException e) {
+            throw new RuntimeException(e);
+        }
+    }
 }
\ No newline at end of file
@@ -29,13 +29,16 @@ public class Runner {
             model.ServerMessage message = model.ServerMessage.readFrom(inputStream);
             if (message instanceof model.ServerMessage.GetAction) {
                 model.ServerMessage.GetAction getActionMessage = (model.ServerMessage.GetAction) message;
-                new model.ClientMessage.ActionMessage(myStrategy.getAction(getActionMessage.getPlayerView(), getActionMessage.isDebugAvailable() ? debugInterface : null)).writeTo(outputStream);
+                model.ClientMessage.ActionMessage actionMessage = new model.ClientMessage.ActionMessage(myStrategy.getAction(getActionMessage.getPlayerView(), getActionMessage.isDebugAvailable() ? debugInterface : null));
+                debugInterface.flush();
+                actionMessage.writeTo(outputStream);
                 outputStream.flush();
             } else if (message instanceof model.ServerMessage.Finish) {
                 break;
             } else if (message instanceof model.ServerMessage.DebugUpdate) {
                 model.ServerMessage.DebugUpdate debugUpdateMessage = (model.ServerMessage.DebugUpdate) message;
                 myStrategy.debugUpdate(debugUpdateMessage.getPlayerView(), debugInterface);
+                debugInterface.flush();
                 new model.ClientMessage.DebugUpdateDone().writeTo(outputStream);
                 outputStream.flush();
             } else {
@@ -3,18 +3,40 @@ const model = require('./model/index');
 class DebugInterface {
     constructor(streamWrapper) {
         this.streamWrapper = streamWrapper;
+        this.commands = [];
     }
 
     async send(command) {
-        await (new model.ClientMessage.DebugMessage(command)).writeTo(this.streamWrapper);
+        this.commands.push(command);
+    }
+
+    async breakpoint(label) {
+        await this.send(new model.DebugCommand.Breakpoint(label));
+    }
+
+    async flush() {
+        if (this.commands.length == 0) {
+            return;
+        }
+        const commands = this.commands;
+        this.commands = [];
+        await (new model.ClientMessage.DebugMessages(commands)).writeTo(this.streamWrapper);
         // TODO: only flush stream once here?
     }
 
     async getState() {
+        await this.flush();
         await (new model.ClientMessage.RequestDebugState()).writeTo(this.streamWrapper);
         // TODO: only flush stream once here?
         return await model.DebugState.readFrom(this.streamWrapper);
     }
+
+    async query(query) {
+        await this.flush();
+        await (new model.ClientMessage.RequestDebugQuery(query)).writeTo(this.streamWrapper);
+        // TODO: only flush stream once here?
+        return await model.DebugQueryResponse.readFrom(this.streamWrapper);
+    }
 }
 
 module.exports.DebugInterface = DebugInterface;
\ No newline at end of file
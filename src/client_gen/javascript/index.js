@@ -44,12 +44,15 @@ class Runner {
             while (true) {
                 message = await model.ServerMessage.readFrom(this.streamWrapper);
                 if (message instanceof model.ServerMessage.GetAction) {
-                    await (new model.ClientMessage.ActionMessage(await strategy.getAction(message.playerView, message.debugAvailable ? debugInterface : null)).writeTo(this.streamWrapper));
+                    const action = await strategy.getAction(message.playerView, message.debugAvailable ? debugInterface : null);
+                    await debugInterface.flush();
+                    await (new model.ClientMessage.ActionMessage(action).writeTo(this.streamWrapper));
                     // TODO: only flush stream once here?
                 } else if (message instanceof model.ServerMessage.Finish) {
                     break;
                 } else if (message instanceof model.ServerMessage.DebugUpdate) {
                     await strategy.debugUpdate(message.playerView, debugInterface);
+                    await debugInterface.flush();
                     await (new model.ClientMessage.DebugUpdateDone().writeTo(this.streamWrapper));
                     // TODO: only flush stream once here?
                 } else {
@@ -3,15 +3,27 @@ import java.io.InputStream
 import java.io.OutputStream
 
 class DebugInterface(private val inputStream: InputStream, private val outputStream: OutputStream) {
+    private val commands = mutableListOf<model.DebugCommand>()
     fun send(command: model.DebugCommand) {
+        commands.add(command)
+    }
+    fun breakpoint(label: String) {
+        send(model.DebugCommand.Breakpoint(label))
+    }
+    fun flush() {
+        if (commands.isEmpty()) {
+            return
+        }
         try {
-            model.ClientMessage.DebugMessage(command).writeTo(outputStream)
+            model.ClientMessage.DebugMessages(commands.toTypedArray()).writeTo(outputStream)
             outputStream.flush()
         } catch (e: IOException) {
             throw RuntimeException(e)
         }
+        commands.clear()
     }
     fun getState(): model.DebugState {
+        flush()
         try {
             model.ClientMessage.RequestDebugState().writeTo(outputStream)
             outputStream.flush()
@@ -20,4 +32,14 @@ class DebugInterface(private val inputStream: InputStream, private val outputStr
             throw RuntimeException(e)
         }
     }
+    fun query(query: model.DebugQuery): model.DebugQueryResponse {
+        flush()
+        try {
+            model.ClientMessage.RequestDebugQuery(query).writeTo(outputStream)
+            outputStream.flush()
+            return model.DebugQueryResponse.readFrom(inputStream)
+        } catch (e: IOException) {
+            throw RuntimeException(e)
+        }
+    }
 }
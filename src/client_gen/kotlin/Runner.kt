@@ -27,12 +27,15 @@ internal constructor(host: String, port: Int, token: String) {
         while (true) {
             val message = model.ServerMessage.readFrom(inputStream)
             if (message is model.ServerMessage.GetAction) {
-                model.ClientMessage.ActionMessage(myStrategy.getAction(message.playerView, if (message.debugAvailable) debugInterface else null)).writeTo(outputStream)
+                val action = myStrategy.getAction(message.playerView, if (message.debugAvailable) debugInterface else null)
+                debugInterface.flush()
+                model.ClientMessage.ActionMessage(action).writeTo(outputStream)
                 outputStream.flush()
             } else if (message is model.ServerMessage.Finish) {
                 break
             } else if (message is model.ServerMessage.DebugUpdate) {
                 myStrategy.debugUpdate(message.playerView, debugInterface)
+                debugInterface.flush()
                 model.ClientMessage.DebugUpdateDone().writeTo(outputStream)
                 outputStream.flush()
             }
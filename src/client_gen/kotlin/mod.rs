@@ -32,6 +32,7 @@ impl<G: Game> ClientGen<G> for trans_gen::GeneratorImpl<Generator> {
         gen.add(&trans::Schema::of::<ClientMessage<G>>());
         gen.add(&trans::Schema::of::<ServerMessage<G>>());
         gen.add(&trans::Schema::of::<G::DebugState>());
+        gen.add(&trans::Schema::of::<G::DebugQueryResponse>());
         gen.result().write_to(&src_path)?;
         write_file(
             src_path.join("MyStrategy.kt"),
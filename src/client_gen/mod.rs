@@ -207,10 +207,11 @@ where
             Box::new(futures::executor::block_on(client_player)?) as Box<_>,
             Box::new(EmptyPlayer) as Box<_>,
         ];
-        let processor = GameProcessor::new(None, default(), players);
+        let processor = GameProcessor::new(None, default(), players)?;
         processor.run(Some(&DebugInterface {
-            debug_command_handler: Box::new(|_player_index, _global, _command| {}),
-            debug_state: Box::new(|_player_index| default()),
+            debug_command_handler: Arc::new(|_player_index, _global, _command| {}),
+            debug_state: Arc::new(|_player_index| Some(default())),
+            debug_query: Arc::new(|_player_index, _query| Some(default())),
         }));
         match client_thread.join() {
             Ok(duration) => info!("Client running time: {} ms", duration.as_millis()),
@@ -221,6 +222,43 @@ where
     Ok(())
 }
 
+/// Call `f` with options for the language, unless it is filtered out.
+/// Every language gets its own subdirectory unless it is the only one selected
+fn for_language<G: Game, CG: ClientGen<G>>(
+    options: &Options,
+    language_filter: &Option<HashSet<&str>>,
+    gen_options: &HashMap<String, serde_json::Value>,
+    f: impl FnOnce(&Options, CG::GenOptions) -> anyhow::Result<()>,
+) -> anyhow::Result<()>
+where
+    CG::GenOptions: for<'de> Deserialize<'de>,
+{
+    if !language_filter
+        .as_ref()
+        .map_or(true, |filter| filter.contains(CG::NAME))
+    {
+        return Ok(());
+    }
+    let language_target_dir = options.target_dir.join(CG::NAME);
+    let options = if language_filter
+        .as_ref()
+        .map_or(false, |filter| filter.len() == 1)
+    {
+        Options { ..*options }
+    } else {
+        Options {
+            target_dir: language_target_dir.as_ref(),
+            ..*options
+        }
+    };
+    let gen_options = match gen_options.get(CG::NAME) {
+        Some(value) => serde_json::from_value(value.clone())
+            .with_context(|| format!("Failed to parse {} gen options", CG::NAME))?,
+        None => default(),
+    };
+    f(&options, gen_options)
+}
+
 pub fn test_all<G>(
     options: &Options,
     extra_files: &HashMap<&str, HashMap<&str, &str>>,
@@ -237,39 +275,22 @@ where
     macro_rules! test {
         ($lang:ident) => {{
             type CG = trans_gen::GeneratorImpl<$lang::Generator>;
-            if language_filter
-                .as_ref()
-                .map_or(true, |filter| filter.contains(<CG as ClientGen<G>>::NAME))
-            {
-                let empty_extra_files = HashMap::new();
-                let language_target_dir = options.target_dir.join(<CG as ClientGen<G>>::NAME);
-                test::<G, CG>(
-                    &if language_filter
-                        .as_ref()
-                        .map_or(false, |filter| filter.len() == 1)
-                    {
-                        Options { ..*options }
-                    } else {
-                        Options {
-                            target_dir: language_target_dir.as_ref(),
-                            ..*options
-                        }
-                    },
-                    gen_options
-                        .get(<CG as ClientGen<G>>::NAME)
-                        .map(|value| {
-                            serde_json::from_value(value.clone())
-                                .expect("Failed to parse gen options")
-                        })
-                        .unwrap_or_default(),
-                    if let Some(extra_files) = extra_files.get(<CG as ClientGen<G>>::NAME) {
+            for_language::<G, CG>(
+                options,
+                &language_filter,
+                gen_options,
+                |options, gen_options| {
+                    let empty_extra_files = HashMap::new();
+                    test::<G, CG>(
+                        options,
+                        gen_options,
                         extra_files
-                    } else {
-                        &empty_extra_files
-                    },
-                    test_options,
-                )?;
-            }
+                            .get(<CG as ClientGen<G>>::NAME)
+                            .unwrap_or(&empty_extra_files),
+                        test_options,
+                    )
+                },
+            )?;
         }};
     }
     if test_options.clean && options.target_dir.exists() {
@@ -279,6 +300,29 @@ where
     Ok(())
 }
 
+pub fn gen_all<G: Game>(
+    options: &Options,
+    language_filter: Option<HashSet<&str>>,
+    gen_options: &HashMap<String, serde_json::Value>,
+) -> anyhow::Result<()> {
+    macro_rules! gen {
+        ($lang:ident) => {{
+            type CG = trans_gen::GeneratorImpl<$lang::Generator>;
+            for_language::<G, CG>(
+                options,
+                &language_filter,
+                gen_options,
+                |options, gen_options| {
+                    info!("Generating {}", <CG as ClientGen<G>>::NAME);
+                    <CG as ClientGen<G>>::gen(options, gen_options)
+                },
+            )?;
+        }};
+    }
+    all_langs!(gen);
+    Ok(())
+}
+
 trait CommandExt {
     fn run(&mut self) -> anyhow::Result<()>;
 }
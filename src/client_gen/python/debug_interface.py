@@ -5,12 +5,29 @@ class DebugInterface:
     def __init__(self, reader, writer):
         self.reader = reader
         self.writer = writer
+        self.commands = []
 
     def send(self, command):
-        model.ClientMessage.DebugMessage(command).write_to(self.writer)
+        self.commands.append(command)
+
+    def breakpoint(self, label):
+        self.send(model.DebugCommand.Breakpoint(label))
+
+    def flush(self):
+        if len(self.commands) == 0:
+            return
+        model.ClientMessage.DebugMessages(self.commands).write_to(self.writer)
         self.writer.flush()
+        self.commands = []
 
     def get_state(self):
+        self.flush()
         model.ClientMessage.RequestDebugState().write_to(self.writer)
         self.writer.flush()
         return model.DebugState.read_from(self.reader)
+
+    def query(self, query):
+        self.flush()
+        model.ClientMessage.RequestDebugQuery(query).write_to(self.writer)
+        self.writer.flush()
+        return model.DebugQueryResponse.read_from(self.reader)
@@ -25,13 +25,16 @@ class Runner:
         while True:
             message = model.ServerMessage.read_from(self.reader)
             if isinstance(message, model.ServerMessage.GetAction):
-                model.ClientMessage.ActionMessage(strategy.get_action(
-                    message.player_view, debug_interface if message.debug_available else None)).write_to(self.writer)
+                action = strategy.get_action(
+                    message.player_view, debug_interface if message.debug_available else None)
+                debug_interface.flush()
+                model.ClientMessage.ActionMessage(action).write_to(self.writer)
                 self.writer.flush()
             elif isinstance(message, model.ServerMessage.Finish):
                 break
             elif isinstance(message, model.ServerMessage.DebugUpdate):
                 strategy.debug_update(message.player_view, debug_interface)
+                debug_interface.flush()
                 model.ClientMessage.DebugUpdateDone().write_to(self.writer)
                 self.writer.flush()
             else:
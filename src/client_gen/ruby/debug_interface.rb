@@ -4,16 +4,37 @@ class DebugInterface
     def initialize(reader, writer)
         @reader = reader
         @writer = writer
+        @commands = []
     end
 
     def send(command)
-        ClientMessage::DebugMessage.new(command).write_to(@writer)
+        @commands.push(command)
+    end
+
+    def breakpoint(label)
+        send(DebugCommand::Breakpoint.new(label))
+    end
+
+    def flush()
+        if @commands.empty?
+            return
+        end
+        ClientMessage::DebugMessages.new(@commands).write_to(@writer)
         @writer.flush()
+        @commands = []
     end
 
     def get_state()
+        flush()
         ClientMessage::RequestDebugState.new().write_to(@writer)
         @writer.flush()
         return DebugState.read_from(@reader)
     end
+
+    def query(query)
+        flush()
+        ClientMessage::RequestDebugQuery.new(query).write_to(@writer)
+        @writer.flush()
+        return DebugQueryResponse.read_from(@reader)
+    end
 end
\ No newline at end of file
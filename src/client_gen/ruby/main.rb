@@ -56,12 +56,15 @@ class Runner
         while true
             message = ServerMessage.read_from(@reader)
             if message.instance_of? ServerMessage::GetAction
-                ClientMessage::ActionMessage.new(strategy.get_action(message.player_view, message.debug_available ? debug_interface : nil)).write_to(@writer)
+                action = strategy.get_action(message.player_view, message.debug_available ? debug_interface : nil)
+                debug_interface.flush()
+                ClientMessage::ActionMessage.new(action).write_to(@writer)
                 @writer.flush()
             elsif message.instance_of? ServerMessage::Finish
                 break
             elsif message.instance_of? ServerMessage::DebugUpdate
                 strategy.debug_update(message.player_view, debug_interface)
+                debug_interface.flush()
                 ClientMessage::DebugUpdateDone.new().write_to(@writer)
                 @writer.flush()
             else
@@ -29,24 +29,47 @@ struct Runner {
 pub struct DebugInterface<'a> {
     reader: &'a mut dyn std::io::Read,
     writer: &'a mut dyn std::io::Write,
+    commands: Vec<model::DebugCommand>,
 }
 
 impl DebugInterface<'_> {
     fn send(&mut self, command: model::DebugCommand) {
+        self.commands.push(command);
+    }
+    fn breakpoint(&mut self, label: String) {
+        self.send(model::DebugCommand::Breakpoint { label });
+    }
+    fn flush(&mut self) {
         use trans::Trans;
-        model::ClientMessage::DebugMessage { command }
-            .write_to(self.writer)
-            .expect("Failed to write custom debug data");
+        if self.commands.is_empty() {
+            return;
+        }
+        model::ClientMessage::DebugMessages {
+            commands: std::mem::take(&mut self.commands),
+        }
+        .write_to(self.writer)
+        .expect("Failed to write custom debug data");
         self.writer.flush().expect("Failed to flush");
     }
     fn get_state(&mut self) -> model::DebugState {
         use trans::Trans;
+        self.flush();
         model::ClientMessage::RequestDebugState {}
             .write_to(self.writer)
             .expect("Failed to write request debug state message");
         self.writer.flush().expect("Failed to flush");
         model::DebugState::read_from(self.reader).expect("Failed to read debug state")
     }
+    fn query(&mut self, query: model::DebugQuery) -> model::DebugQueryResponse {
+        use trans::Trans;
+        self.flush();
+        model::ClientMessage::RequestDebugQuery { query }
+            .write_to(self.writer)
+            .expect("Failed to write debug query");
+        self.writer.flush().expect("Failed to flush");
+        model::DebugQueryResponse::read_from(self.reader)
+            .expect("Failed to read debug query response")
+    }
 }
 
 impl Runner {
@@ -69,6 +92,7 @@ impl Runner {
         DebugInterface {
             reader: &mut self.reader,
             writer: &mut self.writer,
+            commands: Vec::new(),
         }
     }
     fn run(mut self) -> std::io::Result<()> {
@@ -81,22 +105,24 @@ impl Runner {
                     debug_available,
                 } => {
                     let mut debug_interface = self.debug_interface();
-                    let message = model::ClientMessage::ActionMessage {
-                        action: strategy.get_action(
-                            &player_view,
-                            if debug_available {
-                                Some(&mut debug_interface)
-                            } else {
-                                None
-                            },
-                        ),
-                    };
+                    let action = strategy.get_action(
+                        &player_view,
+                        if debug_available {
+                            Some(&mut debug_interface)
+                        } else {
+                            None
+                        },
+                    );
+                    debug_interface.flush();
+                    let message = model::ClientMessage::ActionMessage { action };
                     message.write_to(&mut self.writer)?;
                     self.writer.flush()?;
                 }
                 model::ServerMessage::Finish {} => break,
                 model::ServerMessage::DebugUpdate { player_view } => {
-                    strategy.debug_update(&player_view, &mut self.debug_interface());
+                    let mut debug_interface = self.debug_interface();
+                    strategy.debug_update(&player_view, &mut debug_interface);
+                    debug_interface.flush();
                     model::ClientMessage::DebugUpdateDone {}.write_to(&mut self.writer)?;
                     self.writer.flush()?;
                 }
@@ -1,14 +1,33 @@
 import java.io.InputStream
 import java.io.OutputStream
 
+import scala.collection.mutable.ArrayBuffer
+
 class DebugInterface(private val inputStream: InputStream, private val outputStream: OutputStream) {
+  private val commands = ArrayBuffer[model.DebugCommand]()
   def send(command: model.DebugCommand) {
-    model.ClientMessage.DebugMessage(command).writeTo(outputStream)
-    outputStream.flush()
+    commands += command
+  }
+  def breakpoint(label: String) {
+    send(model.DebugCommand.Breakpoint(label))
+  }
+  def flush() {
+    if (commands.nonEmpty) {
+      model.ClientMessage.DebugMessages(commands.toSeq).writeTo(outputStream)
+      outputStream.flush()
+      commands.clear()
+    }
   }
   def getState(): model.DebugState = {
+    flush()
     model.ClientMessage.RequestDebugState().writeTo(outputStream)
     outputStream.flush()
     model.DebugState.readFrom(inputStream)
   }
+  def query(query: model.DebugQuery): model.DebugQueryResponse = {
+    flush()
+    model.ClientMessage.RequestDebugQuery(query).writeTo(outputStream)
+    outputStream.flush()
+    model.DebugQueryResponse.readFrom(inputStream)
+  }
 }
\ No newline at end of file
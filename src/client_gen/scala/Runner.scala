@@ -25,11 +25,14 @@ object Runner extends App {
     while (true) {
       model.ServerMessage.readFrom(inputStream) match {
         case model.ServerMessage.GetAction(playerView, debugAvailable) =>
-          model.ClientMessage.ActionMessage(myStrategy.getAction(playerView, if (debugAvailable) Some(debugInterface) else None)).writeTo(outputStream)
+          val action = myStrategy.getAction(playerView, if (debugAvailable) Some(debugInterface) else None)
+          debugInterface.flush()
+          model.ClientMessage.ActionMessage(action).writeTo(outputStream)
           outputStream.flush()
         case model.ServerMessage.Finish() => return
         case model.ServerMessage.DebugUpdate(playerView) =>
           myStrategy.debugUpdate(playerView, debugInterface)
+          debugInterface.flush()
           model.ClientMessage.DebugUpdateDone().writeTo(outputStream)
           outputStream.flush()
       }
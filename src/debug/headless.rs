@@ -0,0 +1,60 @@
+use super::*;
+
+/// Debug interface for running without the app.
+/// Commands are dropped, so observers like `DebugCommandWriter` are needed to keep them.
+/// Queries get default responses
+pub fn headless_debug_interface<G: Game>(debug_state: G::DebugState) -> DebugInterface<G> {
+    DebugInterface {
+        debug_command_handler: Arc::new(|_, _, _| {}),
+        debug_state: Arc::new(move |_| Some(debug_state.clone())),
+        debug_query: Arc::new(|_, _| Some(default())),
+    }
+}
+
+#[derive(Serialize)]
+struct DebugCommandRecord<'a, G: Game> {
+    tick: usize,
+    player_index: usize,
+    global: bool,
+    #[serde(bound = "")]
+    command: &'a DebugCommand<G>,
+}
+
+/// Observer writing all debug commands of players as json lines
+pub struct DebugCommandWriter<W: Write + Send> {
+    writer: W,
+    tick: usize,
+}
+
+impl<W: Write + Send> DebugCommandWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, tick: 0 }
+    }
+}
+
+impl<G: Game, W: Write + Send> GameObserver<G> for DebugCommandWriter<W> {
+    fn tick(&mut self, _actions: &HashMap<usize, G::Action>, _events: &Vec<G::Event>, _game: &G) {
+        self.tick += 1;
+    }
+    fn debug_commands(&mut self, player_index: usize, global: bool, commands: &[DebugCommand<G>]) {
+        for command in commands {
+            let record = DebugCommandRecord {
+                tick: self.tick,
+                player_index,
+                global,
+                command,
+            };
+            let result = serde_json::to_writer(&mut self.writer, &record)
+                .map_err(std::io::Error::from)
+                .and_then(|()| writeln!(self.writer));
+            if let Err(e) = result {
+                warn!("Failed to write debug command: {}", e);
+            }
+        }
+    }
+    fn finish(&mut self, _results: &FullResults<G>) {
+        if let Err(e) = self.writer.flush() {
+            warn!("Failed to write debug commands: {}", e);
+        }
+    }
+}
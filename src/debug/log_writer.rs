@@ -0,0 +1,44 @@
+use super::*;
+
+/// Observer writing log messages of players as json lines
+pub struct DebugLogWriter<W: Write + Send> {
+    writer: W,
+    tick: usize,
+}
+
+impl<W: Write + Send> DebugLogWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, tick: 0 }
+    }
+}
+
+impl<G: Game, W: Write + Send> GameObserver<G> for DebugLogWriter<W> {
+    fn tick(&mut self, _actions: &HashMap<usize, G::Action>, _events: &Vec<G::Event>, _game: &G) {
+        self.tick += 1;
+    }
+    fn debug_commands(&mut self, player_index: usize, _global: bool, commands: &[DebugCommand<G>]) {
+        let mut written = false;
+        for command in commands {
+            if let DebugCommand::Log { level, message } = command {
+                let message = DebugLogMessage {
+                    tick: self.tick,
+                    player_index,
+                    level: *level,
+                    message: message.clone(),
+                };
+                let result = serde_json::to_writer(&mut self.writer, &message)
+                    .map_err(std::io::Error::from)
+                    .and_then(|()| writeln!(self.writer));
+                if let Err(e) = result {
+                    warn!("Failed to write debug log: {}", e);
+                }
+                written = true;
+            }
+        }
+        if written {
+            if let Err(e) = self.writer.flush() {
+                warn!("Failed to write debug log: {}", e);
+            }
+        }
+    }
+}
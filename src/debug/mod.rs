@@ -1,5 +1,55 @@
 use super::*;
 
+mod headless;
+mod log_writer;
+
+pub use headless::*;
+pub use log_writer::*;
+
+/// Layer of data sent with `DebugCommand::Add`
+pub const DEFAULT_DEBUG_LAYER: &str = "";
+
+/// Severity of a log message
+#[trans_doc = "ru:Важность сообщения в логе"]
+#[derive(Debug, Serialize, Deserialize, Trans, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugLogLevel {
+    /// Detailed information for debugging
+    #[trans_doc = "ru:Подробная информация для отладки"]
+    Debug,
+    /// Regular information
+    #[trans_doc = "ru:Обычная информация"]
+    Info,
+    /// Something unexpected
+    #[trans_doc = "ru:Что-то неожиданное"]
+    Warning,
+    /// Something went wrong
+    #[trans_doc = "ru:Что-то пошло не так"]
+    Error,
+}
+
+impl Default for DebugLogLevel {
+    fn default() -> Self {
+        Self::Debug
+    }
+}
+
+/// Log message sent by a player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugLogMessage {
+    pub tick: usize,
+    pub player_index: usize,
+    pub level: DebugLogLevel,
+    pub message: String,
+}
+
+/// Breakpoint set by a player
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugBreakpoint {
+    pub tick: usize,
+    pub player_index: usize,
+    pub label: String,
+}
+
 /// Debug commands that can be sent while debugging with the app
 #[trans_doc = "ru:Команды, которые могут быть отправлены приложению для помощи в отладке"]
 #[derive(Serialize, Deserialize, Trans)]
@@ -12,9 +62,43 @@ pub enum DebugCommand<G: Game> {
         #[trans_doc = "ru:Данные для добавления"]
         data: G::DebugData,
     },
+    /// Add debug data to a named layer of current tick
+    #[trans_doc = "ru:Добавить отладочные данные в именованный слой текущего тика"]
+    AddToLayer {
+        /// Name of the layer
+        #[trans_doc = "ru:Название слоя"]
+        layer: String,
+        /// Data to add
+        #[trans_doc = "ru:Данные для добавления"]
+        data: G::DebugData,
+    },
     /// Clear current tick's debug data
     #[trans_doc = "ru:Очистить отладочные данные текущего тика"]
     Clear,
+    /// Clear current tick's debug data of a named layer
+    #[trans_doc = "ru:Очистить отладочные данные именованного слоя текущего тика"]
+    ClearLayer {
+        /// Name of the layer
+        #[trans_doc = "ru:Название слоя"]
+        layer: String,
+    },
+    /// Log a message for current tick
+    #[trans_doc = "ru:Записать сообщение в лог текущего тика"]
+    Log {
+        /// Severity of the message
+        #[trans_doc = "ru:Важность сообщения"]
+        level: DebugLogLevel,
+        /// Text of the message
+        #[trans_doc = "ru:Текст сообщения"]
+        message: String,
+    },
+    /// Mark current tick as a breakpoint, pausing playback when reached
+    #[trans_doc = "ru:Отметить текущий тик как точку останова, на которой воспроизведение будет приостановлено"]
+    Breakpoint {
+        /// Label of the breakpoint
+        #[trans_doc = "ru:Название точки останова"]
+        label: String,
+    },
     /// Enable/disable auto performing of commands
     #[trans_doc = "ru:Включить/выключить автоматическое выполнение команд"]
     SetAutoFlush {
@@ -28,8 +112,22 @@ pub enum DebugCommand<G: Game> {
 }
 
 pub struct DebugInterface<G: Game> {
-    pub(crate) debug_command_handler: Box<dyn Fn(usize, bool, DebugCommand<G>) + Send>,
-    pub(crate) debug_state: Box<dyn Fn(usize) -> G::DebugState + Send>,
+    pub(crate) debug_command_handler: Arc<dyn Fn(usize, bool, Vec<DebugCommand<G>>) + Send + Sync>,
+    /// Returns `None` if there is no debug state to give
+    pub(crate) debug_state: Arc<dyn Fn(usize) -> Option<G::DebugState> + Send + Sync>,
+    /// Returns `None` if query could not be answered
+    pub(crate) debug_query:
+        Arc<dyn Fn(usize, G::DebugQuery) -> Option<G::DebugQueryResponse> + Send + Sync>,
+}
+
+impl<G: Game> Clone for DebugInterface<G> {
+    fn clone(&self) -> Self {
+        Self {
+            debug_command_handler: self.debug_command_handler.clone(),
+            debug_state: self.debug_state.clone(),
+            debug_query: self.debug_query.clone(),
+        }
+    }
 }
 
 impl<G: Game> DebugInterface<G> {
@@ -50,9 +148,17 @@ pub struct PlayerDebugInterface<'a, G: Game> {
 
 impl<G: Game> PlayerDebugInterface<'_, G> {
     pub fn send(&self, command: DebugCommand<G>) {
-        (self.debug_interface.debug_command_handler)(self.player_index, self.global, command);
+        self.send_all(vec![command]);
+    }
+    /// Send multiple commands, handled all at once
+    pub fn send_all(&self, commands: Vec<DebugCommand<G>>) {
+        (self.debug_interface.debug_command_handler)(self.player_index, self.global, commands);
     }
-    pub fn state(&self) -> G::DebugState {
+    /// `None` when running without the app and the game has no `default_debug_state`
+    pub fn state(&self) -> Option<G::DebugState> {
         (self.debug_interface.debug_state)(self.player_index)
     }
+    pub fn query(&self, query: G::DebugQuery) -> Option<G::DebugQueryResponse> {
+        (self.debug_interface.debug_query)(self.player_index, query)
+    }
 }
@@ -0,0 +1,245 @@
+use super::*;
+
+use serde::ser::{self, Serializer};
+
+/// FNV-1a
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct StateHashError(String);
+
+impl ser::Error for StateHashError {
+    fn custom<T: Display>(message: T) -> Self {
+        Self(message.to_string())
+    }
+}
+
+/// Serializer feeding values to the hash instead of writing them anywhere
+struct StateHasher<'a>(&'a mut Fnv);
+
+/// Entries are hashed separately and summed, so that their order does not matter
+struct MapHasher<'a> {
+    hash: &'a mut Fnv,
+    entry: Fnv,
+    sum: u64,
+    count: u64,
+}
+
+type Result<T = ()> = std::result::Result<T, StateHashError>;
+
+macro_rules! hash_as_u64 {
+    ($($method:ident: $t:ty,)*) => {
+        $(
+            fn $method(self, value: $t) -> Result {
+                self.0.write_u64(value as u64);
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'a> Serializer for StateHasher<'a> {
+    type Ok = ();
+    type Error = StateHashError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = MapHasher<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+    hash_as_u64! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_char: char,
+    }
+    fn serialize_i128(self, value: i128) -> Result {
+        self.0.write(&value.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_u128(self, value: u128) -> Result {
+        self.0.write(&value.to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f32(self, value: f32) -> Result {
+        self.0.write(&value.to_bits().to_le_bytes());
+        Ok(())
+    }
+    fn serialize_f64(self, value: f64) -> Result {
+        self.0.write(&value.to_bits().to_le_bytes());
+        Ok(())
+    }
+    fn serialize_str(self, value: &str) -> Result {
+        self.serialize_bytes(value.as_bytes())
+    }
+    fn serialize_bytes(self, value: &[u8]) -> Result {
+        self.0.write_u64(value.len() as u64);
+        self.0.write(value);
+        Ok(())
+    }
+    fn serialize_none(self) -> Result {
+        self.0.write(&[0]);
+        Ok(())
+    }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result {
+        self.0.write(&[1]);
+        value.serialize(self)
+    }
+    fn serialize_unit(self) -> Result {
+        Ok(())
+    }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result {
+        Ok(())
+    }
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result {
+        self.serialize_u32(variant_index)
+    }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result {
+        value.serialize(self)
+    }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result {
+        self.0.write_u64(variant_index as u64);
+        value.serialize(self)
+    }
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        if let Some(len) = len {
+            self.0.write_u64(len as u64);
+        }
+        Ok(self)
+    }
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.0.write_u64(variant_index as u64);
+        Ok(self)
+    }
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapHasher<'a>> {
+        Ok(MapHasher {
+            hash: self.0,
+            entry: Fnv::new(),
+            sum: 0,
+            count: 0,
+        })
+    }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self> {
+        self.0.write_u64(variant_index as u64);
+        Ok(self)
+    }
+}
+
+macro_rules! impl_compound {
+    ($($tr:ident::$method:ident($($name:ident: $t:ty),*);)*) => {
+        $(
+            impl<'a> ser::$tr for StateHasher<'a> {
+                type Ok = ();
+                type Error = StateHashError;
+                fn $method<T: ?Sized + Serialize>(&mut self, $($name: $t,)* value: &T) -> Result {
+                    $(let _ = $name;)*
+                    value.serialize(StateHasher(&mut *self.0))
+                }
+                fn end(self) -> Result {
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_compound! {
+    SerializeSeq::serialize_element();
+    SerializeTuple::serialize_element();
+    SerializeTupleStruct::serialize_field();
+    SerializeTupleVariant::serialize_field();
+    SerializeStruct::serialize_field(key: &'static str);
+    SerializeStructVariant::serialize_field(key: &'static str);
+}
+
+impl<'a> ser::SerializeMap for MapHasher<'a> {
+    type Ok = ();
+    type Error = StateHashError;
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result {
+        self.entry = Fnv::new();
+        key.serialize(StateHasher(&mut self.entry))
+    }
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result {
+        value.serialize(StateHasher(&mut self.entry))?;
+        self.sum = self.sum.wrapping_add(self.entry.0);
+        self.count += 1;
+        Ok(())
+    }
+    fn end(self) -> Result {
+        self.hash.write_u64(self.count);
+        self.hash.write_u64(self.sum);
+        Ok(())
+    }
+}
+
+/// Hash of a state, stable between runs and builds.
+///
+/// Serialized values are hashed directly without building any intermediate representation.
+/// Map entries are hashed regardless of their iteration order, sets are not,
+/// so unordered sets must be `BTreeSet`. Default of `Game::state_hash`
+pub fn state_hash<T: Serialize>(state: &T) -> u64 {
+    let mut hash = Fnv::new();
+    state
+        .serialize(StateHasher(&mut hash))
+        .expect("Failed to hash state");
+    hash.0
+}
@@ -0,0 +1,209 @@
+use super::*;
+
+use serde_json::Value;
+
+mod hash;
+
+pub use hash::state_hash;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonDifference {
+    /// Path to the differing value, like `$.units[3].position`
+    pub path: String,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+impl Display for JsonDifference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |value: &Option<Value>| match value {
+            Some(value) => value.to_string(),
+            None => "<missing>".to_owned(),
+        };
+        write!(
+            f,
+            "{}: {} != {}",
+            self.path,
+            show(&self.left),
+            show(&self.right),
+        )
+    }
+}
+
+/// Structural difference of two serialized values
+pub fn json_diff(left: &Value, right: &Value) -> Vec<JsonDifference> {
+    let mut differences = Vec::new();
+    json_diff_at(left, right, "$", &mut differences);
+    differences
+}
+
+fn json_diff_at(left: &Value, right: &Value, path: &str, differences: &mut Vec<JsonDifference>) {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, left_value) in left {
+                let path = format!("{}.{}", path, key);
+                match right.get(key) {
+                    Some(right_value) => json_diff_at(left_value, right_value, &path, differences),
+                    None => differences.push(JsonDifference {
+                        path,
+                        left: Some(left_value.clone()),
+                        right: None,
+                    }),
+                }
+            }
+            for (key, right_value) in right {
+                if !left.contains_key(key) {
+                    differences.push(JsonDifference {
+                        path: format!("{}.{}", path, key),
+                        left: None,
+                        right: Some(right_value.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for index in 0..left.len().max(right.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (left.get(index), right.get(index)) {
+                    (Some(left_value), Some(right_value)) => {
+                        json_diff_at(left_value, right_value, &path, differences)
+                    }
+                    (left_value, right_value) => differences.push(JsonDifference {
+                        path,
+                        left: left_value.cloned(),
+                        right: right_value.cloned(),
+                    }),
+                }
+            }
+        }
+        (left, right) => {
+            if left != right {
+                differences.push(JsonDifference {
+                    path: path.to_owned(),
+                    left: Some(left.clone()),
+                    right: Some(right.clone()),
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Divergence {
+    /// Number of ticks processed before states diverged, 0 meaning initial state
+    pub tick: usize,
+    /// Index of the run that diverged from the first one
+    pub run: usize,
+    /// Whether runs disagree on the game being finished
+    pub finished_mismatch: bool,
+    pub differences: Vec<JsonDifference>,
+    /// Why `differences` could not be found, e.g. state not representable as json
+    #[serde(default)]
+    pub diff_error: Option<String>,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Run {} diverged at tick {}", self.run, self.tick)?;
+        if self.finished_mismatch {
+            write!(f, ", runs disagree on game being finished")?;
+        }
+        for difference in &self.differences {
+            write!(f, "\n  {}", difference)?;
+        }
+        if let Some(error) = &self.diff_error {
+            write!(f, "\n  Could not find differences: {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeterminismReport {
+    pub ticks_checked: usize,
+    pub divergence: Option<Divergence>,
+}
+
+impl DeterminismReport {
+    pub fn is_deterministic(&self) -> bool {
+        self.divergence.is_none()
+    }
+    /// Panic describing the divergence if there is one, to be used in tests
+    pub fn assert_deterministic(&self) {
+        if let Some(divergence) = &self.divergence {
+            panic!("{}", divergence);
+        }
+    }
+}
+
+fn compare<G: Game>(processors: &[GameProcessor<G>], tick: usize) -> Option<Divergence> {
+    let first = processors[0].game();
+    let first_hash = first.state_hash();
+    for (run, processor) in processors.iter().enumerate().skip(1) {
+        let game = processor.game();
+        let finished_mismatch = game.finished() != first.finished();
+        if finished_mismatch || game.state_hash() != first_hash {
+            let (differences, diff_error) = match serde_json::to_value(first)
+                .and_then(|first| Ok((first, serde_json::to_value(game)?)))
+            {
+                Ok((first, game)) => (json_diff(&first, &game), None),
+                Err(e) => (Vec::new(), Some(e.to_string())),
+            };
+            return Some(Divergence {
+                tick,
+                run,
+                finished_mismatch,
+                differences,
+                diff_error,
+            });
+        }
+    }
+    None
+}
+
+/// Advance processors in lockstep, comparing game states after every tick.
+///
+/// Processors should be created with the same seed and options, and deterministic players
+pub fn check_determinism_with<G: Game + 'static>(
+    mut processors: Vec<GameProcessor<G>>,
+) -> DeterminismReport {
+    assert!(!processors.is_empty(), "No processors to check");
+    let mut tick = 0;
+    loop {
+        if let Some(divergence) = compare(&processors, tick) {
+            return DeterminismReport {
+                ticks_checked: tick,
+                divergence: Some(divergence),
+            };
+        }
+        if processors[0].finished() {
+            break;
+        }
+        for processor in &mut processors {
+            processor.process_tick(None);
+        }
+        tick += 1;
+    }
+    DeterminismReport {
+        ticks_checked: tick,
+        divergence: None,
+    }
+}
+
+/// Run the game from `full_options` several times with the same seed, comparing states after every tick.
+///
+/// Players are created separately for every run, so they must be deterministic and able to
+/// be instantiated `runs` times at once (e.g. not waiting on the same tcp port)
+pub fn check_determinism<G: Game + 'static>(
+    full_options: &FullOptions<G>,
+    runs: usize,
+) -> std::io::Result<DeterminismReport> {
+    assert!(runs >= 2, "Need at least two runs to check determinism");
+    let mut full_options = full_options.clone();
+    full_options.seed = Some(full_options.seed.unwrap_or_else(|| global_rng().gen()));
+    Ok(check_determinism_with(
+        (0..runs)
+            .map(|_| GameProcessor::new_full(full_options.clone()))
+            .collect::<std::io::Result<_>>()?,
+    ))
+}
@@ -0,0 +1,237 @@
+use super::*;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuzzOptions {
+    /// Seed of the first game, next games use following seeds
+    pub seed: u64,
+    pub games: usize,
+    pub player_count: usize,
+    /// Games that are not finished after this many ticks are considered passed
+    pub max_ticks: usize,
+    /// Try removing actions from failing games while they still fail
+    pub shrink: bool,
+    /// Where to save replays of failed games
+    pub save_dir: Option<std::path::PathBuf>,
+}
+
+impl Default for FuzzOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            games: 100,
+            player_count: 2,
+            max_ticks: 1000,
+            shrink: true,
+            save_dir: None,
+        }
+    }
+}
+
+/// Everything needed to reproduce a fuzzed game: seed and actions of every tick
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FuzzReplay<G: Game> {
+    pub seed: u64,
+    pub player_count: usize,
+    #[serde(bound = "")]
+    pub options: G::Options,
+    #[serde(bound = "")]
+    pub actions: Vec<HashMap<usize, G::Action>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FuzzPanicLocation {
+    Init,
+    PlayerView,
+    ProcessTurn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzPanic {
+    pub location: FuzzPanicLocation,
+    pub tick: usize,
+    pub message: String,
+}
+
+impl Display for FuzzPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} panicked on tick {}: {}",
+            self.location, self.tick, self.message,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzFailure<G: Game> {
+    pub panic: FuzzPanic,
+    #[serde(bound = "")]
+    pub replay: FuzzReplay<G>,
+}
+
+impl<G: Game> FuzzFailure<G> {
+    pub fn save(&self, writer: impl Write) -> std::io::Result<()> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+    pub fn load(reader: impl Read) -> std::io::Result<Self> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+fn catch<T>(
+    location: FuzzPanicLocation,
+    tick: usize,
+    f: impl FnOnce() -> T,
+) -> Result<T, FuzzPanic> {
+    catch_panic(f).map_err(|message| FuzzPanic {
+        location,
+        tick,
+        message,
+    })
+}
+
+impl<G: Game> FuzzReplay<G> {
+    fn init(&self) -> Result<(G, StdRng), FuzzPanic> {
+        let mut rng = <StdRng as SeedableRng>::seed_from_u64(self.seed);
+        let game = catch(FuzzPanicLocation::Init, 0, || {
+            G::init(&mut rng, self.player_count, self.options.clone())
+        })?;
+        Ok((game, rng))
+    }
+
+    fn check_views(&self, game: &G, tick: usize) -> Result<(), FuzzPanic> {
+        for player_index in 0..self.player_count {
+            if game.player_active(player_index) {
+                catch(FuzzPanicLocation::PlayerView, tick, || {
+                    game.player_view(player_index)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replay the game, returning the panic if it happens again
+    pub fn run(&self) -> Result<(), FuzzPanic> {
+        let (mut game, mut rng) = self.init()?;
+        for (tick, actions) in self.actions.iter().enumerate() {
+            self.check_views(&game, tick)?;
+            catch(FuzzPanicLocation::ProcessTurn, tick, || {
+                game.process_turn(&mut rng, actions.clone())
+            })?;
+            if game.finished() {
+                return Ok(());
+            }
+        }
+        // Views of the tick after the last recorded one may panic before any action is known
+        self.check_views(&game, self.actions.len())
+    }
+
+    /// Remove ticks after the panic and actions that are not needed to reproduce it
+    fn shrink(&mut self, panic: &FuzzPanic) {
+        self.actions.truncate(panic.tick + 1);
+        let reproduces = |replay: &Self| match replay.run() {
+            Err(new_panic) => {
+                new_panic.location == panic.location && new_panic.message == panic.message
+            }
+            Ok(()) => false,
+        };
+        for tick in (0..self.actions.len()).rev() {
+            let mut players: Vec<usize> = self.actions[tick].keys().copied().collect();
+            players.sort();
+            for player_index in players {
+                let action = self.actions[tick].remove(&player_index).unwrap();
+                if !reproduces(self) {
+                    self.actions[tick].insert(player_index, action);
+                }
+            }
+        }
+    }
+}
+
+/// Run many seeded games with actions produced by `generate_action`,
+/// collecting panics in `Game::player_view` and `Game::process_turn`.
+///
+/// `generate_action` gets index of the player, its view and an rng,
+/// and may return random or deliberately invalid actions.
+/// Fails only if failures could not be saved
+pub fn fuzz<G: Game>(
+    options: &FuzzOptions,
+    game_options: G::Options,
+    mut generate_action: impl FnMut(usize, &G::PlayerView, &mut dyn RngCore) -> G::Action,
+) -> std::io::Result<Vec<FuzzFailure<G>>> {
+    let mut failures = Vec::new();
+    for game_index in 0..options.games {
+        let seed = options.seed.wrapping_add(game_index as u64);
+        let mut action_rng = <StdRng as SeedableRng>::seed_from_u64(!seed);
+        let mut replay = FuzzReplay::<G> {
+            seed,
+            player_count: options.player_count,
+            options: game_options.clone(),
+            actions: Vec::new(),
+        };
+        let result = (|| -> Result<(), FuzzPanic> {
+            let (mut game, mut rng) = replay.init()?;
+            for tick in 0..options.max_ticks {
+                if game.finished() {
+                    break;
+                }
+                let mut actions = HashMap::new();
+                for player_index in 0..options.player_count {
+                    if game.player_active(player_index) {
+                        let view = catch(FuzzPanicLocation::PlayerView, tick, || {
+                            game.player_view(player_index)
+                        })?;
+                        actions.insert(
+                            player_index,
+                            generate_action(player_index, &view, &mut action_rng),
+                        );
+                    }
+                }
+                replay.actions.push(actions.clone());
+                catch(FuzzPanicLocation::ProcessTurn, tick, || {
+                    game.process_turn(&mut rng, actions)
+                })?;
+            }
+            Ok(())
+        })();
+        if let Err(panic) = result {
+            warn!("Game with seed {} failed: {}", seed, panic);
+            if options.shrink {
+                replay.shrink(&panic);
+            }
+            let failure = FuzzFailure { panic, replay };
+            if let Some(dir) = &options.save_dir {
+                std::fs::create_dir_all(dir)?;
+                failure.save(std::io::BufWriter::new(std::fs::File::create(
+                    dir.join(format!("failure-{}.json", seed)),
+                )?))?;
+            }
+            failures.push(failure);
+        }
+    }
+    Ok(failures)
+}
+
+/// Bytes of randomness given to `arbitrary` for every action
+#[cfg(feature = "arbitrary")]
+const ARBITRARY_ACTION_BYTES: usize = 1024;
+
+/// Same as `fuzz`, but actions are generated with `arbitrary::Arbitrary` from random bytes,
+/// ignoring player views. Requires `arbitrary` feature
+#[cfg(feature = "arbitrary")]
+pub fn fuzz_arbitrary<G: Game>(
+    options: &FuzzOptions,
+    game_options: G::Options,
+) -> std::io::Result<Vec<FuzzFailure<G>>>
+where
+    G::Action: for<'a> arbitrary::Arbitrary<'a>,
+{
+    let mut bytes = vec![0; ARBITRARY_ACTION_BYTES];
+    fuzz(options, game_options, move |_, _, rng| loop {
+        rng.fill_bytes(&mut bytes);
+        // Not every byte sequence has to be valid, so try again with new ones
+        if let Ok(action) = arbitrary::Unstructured::new(&bytes).arbitrary() {
+            return action;
+        }
+    })
+}
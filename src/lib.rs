@@ -2,23 +2,38 @@ pub mod prelude {
     pub use batbox::*;
     #[cfg(feature = "rendering")]
     pub use geng::{self, prelude::*};
+    pub use schemars::{self, JsonSchema};
 }
 
 use prelude::*;
 
 #[cfg(feature = "rendering")]
 mod app;
+mod cli;
 #[cfg(feature = "client-gen")]
 pub mod client_gen;
 mod debug;
+mod determinism;
+mod fuzz;
+mod overrides;
 mod player;
 mod processor;
+mod rating;
+mod replay;
+mod schema;
 
 #[cfg(feature = "rendering")]
 pub use app::*;
+pub use cli::*;
 pub use debug::*;
+pub use determinism::*;
+pub use fuzz::*;
+pub use overrides::*;
 pub use player::*;
 pub use processor::*;
+pub use rating::*;
+pub use replay::*;
+pub use schema::*;
 
 pub trait PlayerOptions<G: Game>: From<TcpPlayerOptions> + From<EmptyPlayerOptions> {
     fn get(&self) -> Pin<Box<dyn Future<Output = Result<Box<dyn Player<G>>, PlayerError>>>>;
@@ -39,9 +54,15 @@ where
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[schemars(rename = "GameInitConfig", bound = "")]
 pub enum GameInitConfig<G: Game> {
     LoadFrom(std::path::PathBuf),
+    /// State at the given tick of a saved replay
+    LoadFromReplay {
+        path: std::path::PathBuf,
+        tick: usize,
+    },
     Create(G::OptionsPreset),
 }
 
@@ -52,46 +73,242 @@ impl<G: Game> From<GameInitConfig<G>> for GameInitOptions<G> {
                 serde_json::from_reader(std::fs::File::open(path).expect("Failed to read file"))
                     .expect("Failed to parse file"),
             ),
+            GameInitConfig::LoadFromReplay { path, tick } => Self::Ready(
+                read_replay_state(
+                    std::io::BufReader::new(
+                        std::fs::File::open(path).expect("Failed to open replay"),
+                    ),
+                    tick,
+                )
+                .expect("Failed to read replay"),
+            ),
             GameInitConfig::Create(preset) => Self::New(preset.into()),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[schemars(rename = "FullOptions", bound = "")]
 pub struct FullOptions<G: Game> {
     pub seed: Option<u64>,
     #[serde(bound = "")]
     pub game: GameInitConfig<G>,
     pub players: Vec<G::PlayerOptions>,
+    /// Team index of every player, each player is in its own team if not specified
+    #[serde(default)]
+    pub teams: Option<Vec<usize>>,
+    #[serde(default)]
+    pub real_time: Option<RealTimeOptions>,
+    /// Where to save the last state before a panic in game logic
+    #[serde(default)]
+    pub post_mortem_replay: Option<std::path::PathBuf>,
+    /// Where to write tick profile, `CODEGAME_PROFILE` environment variable is used if not set
+    #[serde(default)]
+    pub profile: Option<ProfilerOptions>,
 }
 
 impl<G: Game> FullOptions<G> {
     pub fn save(&self, writer: impl Write) -> std::io::Result<()> {
         Ok(serde_json::to_writer_pretty(writer, self)?)
     }
+    /// Load options, reporting path and reason of every error if they are invalid
     pub fn load(reader: impl Read) -> std::io::Result<Self> {
-        Ok(serde_json::from_reader(reader)?)
+        Self::from_json_value(serde_json::from_reader(reader)?)
+    }
+    /// Same as `load`, but from already parsed json
+    pub fn from_json_value(value: serde_json::Value) -> std::io::Result<Self> {
+        let errors = Self::validate(&value);
+        if !errors.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                errors
+                    .iter()
+                    .map(|error| error.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ));
+        }
+        let options: Self = serde_json::from_value(value)?;
+        options.check()?;
+        Ok(options)
+    }
+    /// Check values that deserialize fine but can not be used
+    pub fn check(&self) -> std::io::Result<()> {
+        if let Some(teams) = &self.teams {
+            check_teams(teams, self.players.len())?;
+        }
+        if let Some(real_time) = &self.real_time {
+            real_time.check()?;
+        }
+        Ok(())
+    }
+    pub fn validate(value: &serde_json::Value) -> Vec<SchemaError> {
+        validate_json(&json_schema_document::<Self>("FullOptions"), value)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[schemars(rename = "FullResults", bound = "")]
 pub struct FullResults<G: Game> {
     players: Vec<PlayerResult>,
-    results: G::Results,
+    #[serde(default)]
+    teams: Vec<TeamResult>,
+    /// Missing if the game panicked while getting them
+    results: Option<G::Results>,
     seed: Option<u64>,
+    /// Panic message if the game ended because of a panic in game logic
+    #[serde(default)]
+    internal_error: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl<G: Game> FullResults<G> {
+    pub fn players(&self) -> &[PlayerResult] {
+        &self.players
+    }
+    pub fn teams(&self) -> &[TeamResult] {
+        &self.teams
+    }
+    pub fn results(&self) -> Option<&G::Results> {
+        self.results.as_ref()
+    }
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    pub fn internal_error(&self) -> Option<&str> {
+        self.internal_error.as_deref()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
 pub struct PlayerResult {
     crashed: bool,
     comment: Option<String>,
+    #[serde(default)]
+    crash_tick: Option<usize>,
+    #[serde(default)]
+    ticks_played: usize,
+    #[serde(default)]
+    total_response_time: f64,
+    #[serde(default)]
+    max_response_time: f64,
+    #[serde(default)]
+    bytes_sent: u64,
+    #[serde(default)]
+    bytes_received: u64,
+    #[serde(default)]
+    debug_commands: usize,
+}
+
+impl PlayerResult {
+    pub fn crashed(&self) -> bool {
+        self.crashed
+    }
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_ref().map(|comment| comment.as_str())
+    }
+    /// Tick during which the player crashed
+    pub fn crash_tick(&self) -> Option<usize> {
+        self.crash_tick
+    }
+    /// Number of ticks the player has sent an action for
+    pub fn ticks_played(&self) -> usize {
+        self.ticks_played
+    }
+    /// Total time spent waiting for player's actions, in seconds
+    pub fn total_response_time(&self) -> f64 {
+        self.total_response_time
+    }
+    /// Longest time spent waiting for a single action, in seconds
+    pub fn max_response_time(&self) -> f64 {
+        self.max_response_time
+    }
+    /// Bytes sent to the player
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+    /// Bytes received from the player
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+    /// Number of debug commands sent by the player
+    pub fn debug_commands(&self) -> usize {
+        self.debug_commands
+    }
+    pub(crate) fn record_action(&mut self, response_time: f64, traffic: PlayerTraffic) {
+        self.ticks_played += 1;
+        self.total_response_time += response_time;
+        self.max_response_time = partial_max(self.max_response_time, response_time);
+        self.record_traffic(traffic);
+    }
+    pub(crate) fn record_traffic(&mut self, traffic: PlayerTraffic) {
+        self.bytes_sent = traffic.bytes_sent;
+        self.bytes_received = traffic.bytes_received;
+    }
+    pub(crate) fn record_crash(&mut self, tick: usize, error: &PlayerError) {
+        warn!("Player error: {}", error);
+        self.crashed = true;
+        self.crash_tick = Some(tick);
+        self.comment = Some(format!("Player crashed: {}", error));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct TeamResult {
+    players: Vec<usize>,
+    /// Whether all players of the team crashed
+    crashed: bool,
+    /// Sum of scores of team's players, if game reports them
+    #[serde(default)]
+    score: Option<f64>,
+}
+
+impl TeamResult {
+    pub fn players(&self) -> &[usize] {
+        &self.players
+    }
+    pub fn crashed(&self) -> bool {
+        self.crashed
+    }
+    pub fn score(&self) -> Option<f64> {
+        self.score
+    }
+}
+
+pub fn default_teams(player_count: usize) -> Vec<usize> {
+    (0..player_count).collect()
+}
+
+/// Check that every player has a team, and teams are numbered below player count
+pub fn check_teams(teams: &[usize], player_count: usize) -> std::io::Result<()> {
+    if teams.len() != player_count {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "teams has {} entries, but there are {} players",
+                teams.len(),
+                player_count,
+            ),
+        ));
+    }
+    if let Some(&team) = teams.iter().find(|&&team| team >= player_count) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "team {} is out of range, there are {} players",
+                team, player_count,
+            ),
+        ));
+    }
+    Ok(())
 }
 
 pub trait Game: Diff {
     type Options: Serialize + for<'de> Deserialize<'de> + Sync + Send + Clone + 'static;
+    /// `JsonSchema` is used to validate config files, derive it with `#[derive(JsonSchema)]`
     type OptionsPreset: Debug
         + Serialize
         + for<'de> Deserialize<'de>
+        + JsonSchema
         + Sync
         + Send
         + Clone
@@ -100,6 +317,7 @@ pub trait Game: Diff {
     type PlayerOptions: PlayerOptions<Self>
         + Serialize
         + for<'de> Deserialize<'de>
+        + JsonSchema
         + Sync
         + Send
         + Clone
@@ -107,11 +325,35 @@ pub trait Game: Diff {
     type Action: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
     type Event: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
     type PlayerView: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
-    type Results: Serialize + for<'de> Deserialize<'de> + Sync + Send + Clone + 'static;
+    type Results: Serialize + for<'de> Deserialize<'de> + JsonSchema + Sync + Send + Clone + 'static;
     type DebugData: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
     type DebugState: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
+    /// Question about the state of the app, answered by `Renderer::debug_query`.
+    ///
+    /// Required since debug queries were added, games that don't use them
+    /// can declare an empty struct for it and for `DebugQueryResponse`
+    type DebugQuery: Serialize + for<'de> Deserialize<'de> + Trans + Sync + Send + Clone + 'static;
+    /// Answer to `DebugQuery`, default one is given when there is no app to answer
+    type DebugQueryResponse: Serialize
+        + for<'de> Deserialize<'de>
+        + Trans
+        + Default
+        + Sync
+        + Send
+        + Clone
+        + 'static;
     fn init(rng: &mut dyn RngCore, player_count: usize, options: Self::Options) -> Self;
+    /// Same as `init`, with team index given for every player
+    fn init_with_teams(rng: &mut dyn RngCore, teams: &[usize], options: Self::Options) -> Self {
+        Self::init(rng, teams.len(), options)
+    }
     fn player_view(&self, player_index: usize) -> Self::PlayerView;
+    /// Whether player needs to act on current tick.
+    /// Only active players are asked for an action, so `process_turn` gets actions of active players only
+    fn player_active(&self, player_index: usize) -> bool {
+        #![allow(unused_variables)]
+        true
+    }
     fn process_turn(
         &mut self,
         rng: &mut dyn RngCore,
@@ -119,6 +361,23 @@ pub trait Game: Diff {
     ) -> Vec<Self::Event>;
     fn finished(&self) -> bool;
     fn results(&self) -> Self::Results;
+    /// Score of every player, summed up per team in `FullResults`.
+    /// Teams have no score if `None`
+    fn player_scores(results: &Self::Results) -> Option<Vec<f64>> {
+        #![allow(unused_variables)]
+        None
+    }
+    /// Debug state given to players when running without the app.
+    /// Debugging without the app is rejected if `None`, since players may request it
+    fn default_debug_state() -> Option<Self::DebugState> {
+        None
+    }
+    /// Hash of the state, saved in replays and compared when checking determinism.
+    /// Must be the same for equal states, so states with unordered sets must either
+    /// use `BTreeSet` or override this
+    fn state_hash(&self) -> u64 {
+        state_hash(self)
+    }
 }
 
 /// Message sent from client
@@ -147,6 +406,21 @@ pub enum ClientMessage<G: Game> {
     /// Request debug state from the app
     #[trans_doc = "ru:Запросить отладочное состояние приложения"]
     RequestDebugState {},
+    /// Ask app to perform multiple debug commands at once
+    #[trans_doc = "ru:Отправить приложению несколько отладочных команд за раз"]
+    DebugMessages {
+        /// Commands to perform, in order
+        #[trans_doc = "ru:Команды для исполнения по порядку"]
+        #[serde(bound = "")]
+        commands: Vec<DebugCommand<G>>,
+    },
+    /// Ask the app a question, answered with debug query response
+    #[trans_doc = "ru:Задать вопрос приложению, ответом будет ответ на отладочный запрос"]
+    RequestDebugQuery {
+        /// The question
+        #[trans_doc = "ru:Вопрос"]
+        query: G::DebugQuery,
+    },
 }
 
 /// Message sent from server
@@ -217,6 +491,44 @@ pub trait Renderer<G: Game>: 'static {
         #![allow(unused_variables)]
     }
     fn debug_state(&self, game: &G, player_index: usize) -> G::DebugState;
+    /// Answer a question of a player, default response by default
+    fn debug_query(
+        &self,
+        game: &G,
+        player_index: usize,
+        query: G::DebugQuery,
+    ) -> G::DebugQueryResponse {
+        #![allow(unused_variables)]
+        default()
+    }
+}
+
+/// Rebuild game state at the given tick of a replay saved by `save_replay_tick_handler`
+pub fn read_replay_state<G: Game>(reader: impl BufRead, tick: usize) -> std::io::Result<G> {
+    let mut replay = ReplayReader::<G, _>::new(reader)?;
+    while replay.tick() < tick {
+        if replay.finished()? {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Replay only has {} ticks", replay.tick()),
+            ));
+        }
+        replay.next_tick()?;
+    }
+    Ok(replay.into_game())
+}
+
+/// Run `f`, turning a panic into its message
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "<unknown panic>".to_owned()
+        }
+    })
 }
 
 pub fn save_replay_tick_handler<G: Game, T: Write + Send + 'static>(
@@ -224,14 +536,9 @@ pub fn save_replay_tick_handler<G: Game, T: Write + Send + 'static>(
 ) -> Box<dyn FnMut(Option<&Vec<G::Event>>, &G) + Send> {
     let mut last: Option<G> = None;
     Box::new(move |events: Option<&Vec<G::Event>>, current: &G| {
-        if let Some(events) = events {
-            events
-                .write_to(&mut writer)
-                .expect("Failed to write replay");
-        }
-        match &last {
-            None => current.write_to(&mut writer),
-            Some(last) => last.diff(current).write_to(&mut writer),
+        match (&last, events) {
+            (Some(last), Some(events)) => write_replay_tick(&mut writer, events, last, current),
+            _ => write_replay_start(&mut writer, current),
         }
         .expect("Failed to write replay");
         last = Some(current.clone());
@@ -0,0 +1,195 @@
+use super::*;
+
+use serde_json::Value;
+
+/// Environment variable with `;` separated overrides, applied before command line ones
+pub const OPTIONS_OVERRIDES_ENV: &str = "CODEGAME_OVERRIDES";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionsPathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// Override of a single value in options, like `seed=42` or `players[1]=tcp:31005`.
+///
+/// Value is parsed as json, falling back to a string if that fails.
+/// For `players[<index>]`, `tcp:<port>` and `tcp:<host>:<port>` are shorthands for tcp player options,
+/// IPv6 hosts are written in brackets like `tcp:[::1]:31005`.
+#[derive(Debug, Clone)]
+pub struct OptionsOverride {
+    pub path: Vec<OptionsPathSegment>,
+    pub value: String,
+}
+
+impl Display for OptionsOverride {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}={}", path_to_string(&self.path), self.value)
+    }
+}
+
+fn path_to_string(path: &[OptionsPathSegment]) -> String {
+    let mut result = String::new();
+    for segment in path {
+        match segment {
+            OptionsPathSegment::Field(name) => {
+                if !result.is_empty() {
+                    result.push('.');
+                }
+                result.push_str(name);
+            }
+            OptionsPathSegment::Index(index) => result.push_str(&format!("[{}]", index)),
+        }
+    }
+    result
+}
+
+fn parse_path(path: &str) -> anyhow::Result<Vec<OptionsPathSegment>> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (name, mut rest) = match part.find('[') {
+            Some(index) => part.split_at(index),
+            None => (part, ""),
+        };
+        if !name.is_empty() {
+            segments.push(OptionsPathSegment::Field(name.to_owned()));
+        } else if rest.is_empty() {
+            anyhow::bail!("Empty segment in path {:?}", path);
+        }
+        while !rest.is_empty() {
+            let end = match (rest.starts_with('['), rest.find(']')) {
+                (true, Some(end)) => end,
+                _ => anyhow::bail!("Invalid index in path {:?}", path),
+            };
+            segments.push(OptionsPathSegment::Index(
+                rest[1..end]
+                    .parse()
+                    .with_context(|| format!("Invalid index in path {:?}", path))?,
+            ));
+            rest = &rest[end + 1..];
+        }
+    }
+    Ok(segments)
+}
+
+impl std::str::FromStr for OptionsOverride {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let index = s
+            .find('=')
+            .with_context(|| format!("Expected <path>=<value>, got {:?}", s))?;
+        Ok(Self {
+            path: parse_path(&s[..index])?,
+            value: s[index + 1..].to_owned(),
+        })
+    }
+}
+
+impl OptionsOverride {
+    /// Overrides from `CODEGAME_OVERRIDES` environment variable
+    pub fn from_env() -> anyhow::Result<Vec<Self>> {
+        match std::env::var(OPTIONS_OVERRIDES_ENV) {
+            Ok(overrides) => overrides
+                .split(';')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    s.parse()
+                        .with_context(|| format!("Failed to parse {}", OPTIONS_OVERRIDES_ENV))
+                })
+                .collect(),
+            Err(std::env::VarError::NotPresent) => Ok(Vec::new()),
+            Err(e) => Err(e).context(OPTIONS_OVERRIDES_ENV),
+        }
+    }
+
+    fn is_player_path(&self) -> bool {
+        match self.path.as_slice() {
+            [OptionsPathSegment::Field(name), OptionsPathSegment::Index(_)] => name == "players",
+            _ => false,
+        }
+    }
+
+    fn json_value<G: Game>(&self) -> anyhow::Result<Value> {
+        if let (true, Some(address)) = (self.is_player_path(), self.value.strip_prefix("tcp:")) {
+            let (host, port) = match address.rsplit_once(':') {
+                Some((host, port)) => {
+                    let host = host
+                        .strip_prefix('[')
+                        .and_then(|host| host.strip_suffix(']'))
+                        .unwrap_or(host);
+                    (Some(host.to_owned()), port)
+                }
+                None => (None, address),
+            };
+            let options = TcpPlayerOptions {
+                host,
+                port: port
+                    .parse()
+                    .with_context(|| format!("Invalid port in {:?}", self.value))?,
+                accept_timeout: None,
+                timeout: None,
+                token: None,
+            };
+            return Ok(serde_json::to_value(G::PlayerOptions::from(options))?);
+        }
+        Ok(serde_json::from_str(&self.value).unwrap_or_else(|_| Value::String(self.value.clone())))
+    }
+
+    /// Apply override to options represented as json
+    pub fn apply<G: Game>(&self, options: &mut Value) -> anyhow::Result<()> {
+        let mut current = options;
+        for segment in &self.path {
+            current = match segment {
+                OptionsPathSegment::Field(name) => {
+                    if current.is_null() {
+                        *current = Value::Object(default());
+                    }
+                    match current {
+                        Value::Object(object) => object.entry(name.clone()).or_insert(Value::Null),
+                        _ => anyhow::bail!(
+                            "Can not override {}: {:?} is not an object field",
+                            path_to_string(&self.path),
+                            name,
+                        ),
+                    }
+                }
+                OptionsPathSegment::Index(index) => {
+                    if current.is_null() {
+                        *current = Value::Array(Vec::new());
+                    }
+                    match current {
+                        // Index right after the last element appends a new one
+                        Value::Array(array) if *index <= array.len() => {
+                            if *index == array.len() {
+                                array.push(Value::Null);
+                            }
+                            &mut array[*index]
+                        }
+                        _ => anyhow::bail!(
+                            "Can not override {}: index {} is out of bounds",
+                            path_to_string(&self.path),
+                            index,
+                        ),
+                    }
+                }
+            };
+        }
+        *current = self.json_value::<G>()?;
+        Ok(())
+    }
+}
+
+impl<G: Game> FullOptions<G> {
+    /// Load options and apply overrides from environment and then the given ones
+    pub fn load_with_overrides(
+        reader: impl Read,
+        overrides: &[OptionsOverride],
+    ) -> anyhow::Result<Self> {
+        let mut value: Value = serde_json::from_reader(reader)?;
+        for options_override in OptionsOverride::from_env()?.iter().chain(overrides) {
+            options_override.apply::<G>(&mut value)?;
+        }
+        Ok(Self::from_json_value(value)?)
+    }
+}
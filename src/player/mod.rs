@@ -10,6 +10,15 @@ pub use tcp::*;
 pub enum PlayerError {
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
+    #[error("Panic: {0}")]
+    Panic(String),
+}
+
+/// Amount of data transferred to and from a player
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlayerTraffic {
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
 }
 
 pub trait Player<G: Game>: Send {
@@ -23,11 +32,14 @@ pub trait Player<G: Game>: Send {
         player_view: &G::PlayerView,
         debug_interface: &PlayerDebugInterface<G>,
     ) -> Result<(), PlayerError>;
+    fn traffic(&self) -> PlayerTraffic {
+        default()
+    }
 }
 
 pub struct EmptyPlayer;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct EmptyPlayerOptions;
 
 impl<G: Game> Player<G> for EmptyPlayer
@@ -65,6 +77,9 @@ impl<G: Game, T: Player<G> + ?Sized> Player<G> for Box<T> {
     ) -> Result<(), PlayerError> {
         (**self).debug_update(player_view, debug_interface)
     }
+    fn traffic(&self) -> PlayerTraffic {
+        (**self).traffic()
+    }
 }
 
 pub struct ErroredPlayer(pub String);
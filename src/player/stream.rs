@@ -1,19 +1,75 @@
 use super::*;
 
+use std::sync::atomic::{AtomicU64, Ordering};
+
+struct CountingReader {
+    inner: Box<dyn BufRead + Send>,
+    count: Arc<AtomicU64>,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count.fetch_add(len as u64, Ordering::Relaxed);
+        Ok(len)
+    }
+}
+
+impl BufRead for CountingReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amt: usize) {
+        self.count.fetch_add(amt as u64, Ordering::Relaxed);
+        self.inner.consume(amt);
+    }
+}
+
+struct CountingWriter {
+    inner: Box<dyn Write + Send>,
+    count: Arc<AtomicU64>,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        self.count.fetch_add(len as u64, Ordering::Relaxed);
+        Ok(len)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
 struct Stream {
-    reader: Box<dyn BufRead + Send>,
-    writer: Box<dyn Write + Send>,
+    reader: CountingReader,
+    writer: CountingWriter,
 }
 
 pub struct StreamPlayer<G: Game> {
     stream: Option<Stream>,
+    bytes_sent: Arc<AtomicU64>,
+    bytes_received: Arc<AtomicU64>,
     phantom_data: PhantomData<G>,
 }
 
 impl<G: Game> StreamPlayer<G> {
     pub fn new(reader: Box<dyn BufRead + Send>, writer: Box<dyn Write + Send>) -> Self {
+        let bytes_sent = Arc::new(AtomicU64::new(0));
+        let bytes_received = Arc::new(AtomicU64::new(0));
         Self {
-            stream: Some(Stream { reader, writer }),
+            stream: Some(Stream {
+                reader: CountingReader {
+                    inner: reader,
+                    count: bytes_received.clone(),
+                },
+                writer: CountingWriter {
+                    inner: writer,
+                    count: bytes_sent.clone(),
+                },
+            }),
+            bytes_sent,
+            bytes_received,
             phantom_data: PhantomData,
         }
     }
@@ -33,6 +89,13 @@ impl<G: Game> Drop for StreamPlayer<G> {
     }
 }
 
+fn debug_state_unavailable() -> PlayerError {
+    PlayerError::IOError(std::io::Error::new(
+        std::io::ErrorKind::Other,
+        "Requested debug state with no debug state available",
+    ))
+}
+
 impl<G: Game> Player<G> for StreamPlayer<G> {
     fn get_action(
         &mut self,
@@ -51,14 +114,12 @@ impl<G: Game> Player<G> for StreamPlayer<G> {
                 match ClientMessage::<G>::read_from(&mut stream.reader)? {
                     ClientMessage::ActionMessage { action } => return Ok(action),
                     ClientMessage::RequestDebugState {} => {
-                        if let Some(debug_interface) = debug_interface {
-                            debug_interface.state().write_to(&mut stream.writer)?;
-                            stream.writer.flush()?;
-                        } else {
-                            return Err(PlayerError::IOError(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                "Requested debug state with no debug interface available",
-                            )));
+                        match debug_interface.and_then(|debug_interface| debug_interface.state()) {
+                            Some(state) => {
+                                state.write_to(&mut stream.writer)?;
+                                stream.writer.flush()?;
+                            }
+                            None => return Err(debug_state_unavailable()),
                         }
                     }
                     ClientMessage::DebugMessage { command } => {
@@ -66,6 +127,19 @@ impl<G: Game> Player<G> for StreamPlayer<G> {
                             debug_interface.send(command);
                         }
                     }
+                    ClientMessage::DebugMessages { commands } => {
+                        if let Some(debug_interface) = debug_interface {
+                            debug_interface.send_all(commands);
+                        }
+                    }
+                    ClientMessage::RequestDebugQuery { query } => {
+                        // Client is blocked waiting for an answer, so it gets one anyway
+                        debug_interface
+                            .and_then(|debug_interface| debug_interface.query(query))
+                            .unwrap_or_default()
+                            .write_to(&mut stream.writer)?;
+                        stream.writer.flush()?;
+                    }
                     ClientMessage::DebugUpdateDone {} => {
                         return Err(PlayerError::IOError(std::io::Error::new(
                             std::io::ErrorKind::Other,
@@ -101,13 +175,26 @@ impl<G: Game> Player<G> for StreamPlayer<G> {
                             "Unexpected action message in debug update",
                         )));
                     }
-                    ClientMessage::RequestDebugState {} => {
-                        debug_interface.state().write_to(&mut stream.writer)?;
-                        stream.writer.flush()?;
-                    }
+                    ClientMessage::RequestDebugState {} => match debug_interface.state() {
+                        Some(state) => {
+                            state.write_to(&mut stream.writer)?;
+                            stream.writer.flush()?;
+                        }
+                        None => return Err(debug_state_unavailable()),
+                    },
                     ClientMessage::DebugMessage { command } => {
                         debug_interface.send(command);
                     }
+                    ClientMessage::DebugMessages { commands } => {
+                        debug_interface.send_all(commands);
+                    }
+                    ClientMessage::RequestDebugQuery { query } => {
+                        debug_interface
+                            .query(query)
+                            .unwrap_or_default()
+                            .write_to(&mut stream.writer)?;
+                        stream.writer.flush()?;
+                    }
                     ClientMessage::DebugUpdateDone {} => return Ok(()),
                 }
             }
@@ -118,4 +205,10 @@ impl<G: Game> Player<G> for StreamPlayer<G> {
         }
         result
     }
+    fn traffic(&self) -> PlayerTraffic {
+        PlayerTraffic {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
 }
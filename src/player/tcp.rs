@@ -5,7 +5,7 @@ pub struct TcpPlayer<G: Game> {
     port: u16,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct TcpPlayerOptions {
     pub host: Option<String>,
     pub port: u16,
@@ -112,4 +112,7 @@ impl<G: Game> Player<G> for TcpPlayer<G> {
     ) -> Result<(), PlayerError> {
         Player::<G>::debug_update(&mut self.inner, player_view, debug_interface)
     }
+    fn traffic(&self) -> PlayerTraffic {
+        Player::<G>::traffic(&self.inner)
+    }
 }
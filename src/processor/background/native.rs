@@ -4,8 +4,10 @@ use std::sync::atomic::{AtomicI32, Ordering};
 
 pub struct BackgroundGameProcessor<G: Game> {
     player_count: usize,
+    real_time: Option<RealTimeOptions>,
     ticks_to_process: Arc<AtomicI32>,
-    debug_game_state: Arc<Mutex<Option<G>>>,
+    /// State for debug updates, shared instead of copied so that it is never locked for long
+    debug_game_state: Arc<Mutex<Option<Arc<G>>>>,
     thread: Option<std::thread::JoinHandle<()>>,
     phantom_data: PhantomData<G>,
 }
@@ -17,8 +19,9 @@ impl<G: Game> BackgroundGameProcessor<G> {
         debug_interface: Option<DebugInterface<G>>,
     ) -> Self {
         let player_count = processor.player_count();
+        let real_time = processor.real_time().cloned();
         let ticks_to_process = Arc::new(AtomicI32::new(0));
-        let debug_game_state = Arc::new(Mutex::new(None::<G>));
+        let debug_game_state = Arc::new(Mutex::new(None::<Arc<G>>));
         let thread = std::thread::spawn({
             let ticks_to_process = ticks_to_process.clone();
             let debug_game_state = debug_game_state.clone();
@@ -29,10 +32,10 @@ impl<G: Game> BackgroundGameProcessor<G> {
                         break 'thread_loop;
                     }
                     if let Some(debug_interface) = &debug_interface {
-                        processor.debug_update(
-                            debug_game_state.lock().unwrap().as_ref(),
-                            debug_interface,
-                        );
+                        // Players may wait for the app to answer their queries here,
+                        // so the lock must not be held, or `proceed` would block
+                        let debug_game_state = debug_game_state.lock().unwrap().clone();
+                        processor.debug_update(debug_game_state.as_deref(), debug_interface);
                     }
                     if ticks > 0
                         && ticks_to_process.compare_and_swap(ticks, ticks - 1, Ordering::SeqCst)
@@ -51,6 +54,7 @@ impl<G: Game> BackgroundGameProcessor<G> {
         });
         Self {
             player_count,
+            real_time,
             ticks_to_process,
             debug_game_state,
             thread: Some(thread),
@@ -58,7 +62,7 @@ impl<G: Game> BackgroundGameProcessor<G> {
         }
     }
     pub fn proceed(&mut self, debug_game_state: Option<&G>, max_ticks: usize) {
-        *self.debug_game_state.lock().unwrap() = debug_game_state.cloned();
+        *self.debug_game_state.lock().unwrap() = debug_game_state.cloned().map(Arc::new);
         self.ticks_to_process
             .store(max_ticks as i32, Ordering::SeqCst);
         self.thread.as_ref().unwrap().thread().unpark();
@@ -66,6 +70,9 @@ impl<G: Game> BackgroundGameProcessor<G> {
     pub fn player_count(&self) -> usize {
         self.player_count
     }
+    pub fn real_time(&self) -> Option<&RealTimeOptions> {
+        self.real_time.as_ref()
+    }
 }
 
 impl<G: Game> Drop for BackgroundGameProcessor<G> {
@@ -73,6 +80,8 @@ impl<G: Game> Drop for BackgroundGameProcessor<G> {
         self.ticks_to_process.store(-1, Ordering::SeqCst);
         let thread = self.thread.take().unwrap();
         thread.thread().unpark();
-        thread.join().expect("Failed to stop game processor thread");
+        if thread.join().is_err() {
+            error!("Game processor thread panicked");
+        }
     }
 }
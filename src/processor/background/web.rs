@@ -33,4 +33,7 @@ impl<G: Game> BackgroundGameProcessor<G> {
     pub fn player_count(&self) -> usize {
         self.processor.player_count()
     }
+    pub fn real_time(&self) -> Option<&RealTimeOptions> {
+        self.processor.real_time()
+    }
 }
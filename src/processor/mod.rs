@@ -1,25 +1,39 @@
 use super::*;
 
 mod background;
+mod observer;
 #[path = "strategy/mod.rs"]
 pub mod processor_strategy;
+mod profiler;
+mod real_time;
 
 pub use background::*;
+pub use observer::GameObserver;
 pub use processor_strategy::GameProcessorStrategy;
+pub use profiler::{ProfileFormat, ProfilerOptions, PROFILE_ENV};
+pub use real_time::{LateActionPolicy, MissingActionPolicy, RealTimeOptions};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub struct GameProcessor<G: Game> {
     seed: Option<u64>,
     strategy: Box<dyn GameProcessorStrategy<G>>,
     players: Vec<Option<Box<dyn Player<G>>>>,
-    player_comments: Vec<Option<String>>,
+    teams: Vec<usize>,
+    real_time: Option<real_time::RealTime<G>>,
+    player_results: Vec<PlayerResult>,
+    debug_commands: Arc<Vec<AtomicUsize>>,
     ticks_processed: usize,
-    tick_handler: Option<Box<dyn FnMut(Option<&Vec<G::Event>>, &G) + Send>>,
-    results_handler: Option<Box<dyn FnOnce(FullResults<G>) + Send>>,
+    internal_error: Option<String>,
+    post_mortem_replay: Option<std::path::PathBuf>,
+    last_good_state: Option<G>,
+    observers: Arc<Mutex<Vec<Box<dyn GameObserver<G>>>>>,
+    profiler: Option<profiler::Profiler>,
 }
 
 impl<G: Game + 'static> GameProcessor<G> {
-    pub fn new_full(full_options: FullOptions<G>) -> Self {
-        Self::new(
+    pub fn new_full(full_options: FullOptions<G>) -> std::io::Result<Self> {
+        let mut processor = Self::new_with_teams(
             full_options.seed,
             full_options.game.into(),
             futures::executor::block_on(futures::future::join_all(
@@ -31,35 +45,63 @@ impl<G: Game + 'static> GameProcessor<G> {
                 Err(e) => Box::new(ErroredPlayer(e.to_string())),
             })
             .collect(),
-        )
+            full_options.teams,
+        )?;
+        if let Some(real_time) = full_options.real_time {
+            processor.set_real_time(real_time)?;
+        }
+        if let Some(path) = full_options.post_mortem_replay {
+            processor.set_post_mortem_replay(path);
+        }
+        if let Some(profile) = full_options.profile {
+            processor.set_profiler(profile);
+        }
+        Ok(processor)
     }
     pub fn new(
         seed: Option<u64>,
         options: GameInitOptions<G>,
         players: Vec<Box<dyn Player<G>>>,
-    ) -> Self {
+    ) -> std::io::Result<Self> {
+        Self::new_with_teams(seed, options, players, None)
+    }
+    pub fn new_with_teams(
+        seed: Option<u64>,
+        options: GameInitOptions<G>,
+        players: Vec<Box<dyn Player<G>>>,
+        teams: Option<Vec<usize>>,
+    ) -> std::io::Result<Self> {
+        let teams = teams.unwrap_or_else(|| default_teams(players.len()));
+        check_teams(&teams, players.len())?;
         let seed = seed.unwrap_or_else(|| global_rng().gen());
         let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed);
         let game = match options {
             GameInitOptions::Ready(game) => game,
-            GameInitOptions::New(options) => G::init(&mut rng, players.len(), options),
+            GameInitOptions::New(options) => G::init_with_teams(&mut rng, &teams, options),
         };
-        let player_comments = vec![None; players.len()];
-        Self {
+        let player_results = vec![PlayerResult::default(); players.len()];
+        let debug_commands = Arc::new((0..players.len()).map(|_| AtomicUsize::new(0)).collect());
+        Ok(Self {
             seed: Some(seed),
             strategy: Box::new(processor_strategy::Standard::new(game, rng)),
             players: players.into_iter().map(|player| Some(player)).collect(),
-            player_comments,
+            teams,
+            real_time: None,
+            player_results,
+            debug_commands,
             ticks_processed: 0,
-            tick_handler: None,
-            results_handler: None,
-        }
+            internal_error: None,
+            post_mortem_replay: None,
+            last_good_state: None,
+            observers: Arc::new(Mutex::new(Vec::new())),
+            profiler: ProfilerOptions::from_env().map(profiler::Profiler::new),
+        })
     }
     pub fn repeat_full(
         full_options: FullOptions<G>,
         reader: impl std::io::Read + Send + 'static,
-    ) -> Self {
-        Self::repeat(
+    ) -> std::io::Result<Self> {
+        Self::repeat_with_teams(
             reader,
             futures::executor::block_on(futures::future::join_all(
                 full_options.players.iter().map(|options| options.get()),
@@ -70,35 +112,78 @@ impl<G: Game + 'static> GameProcessor<G> {
                 Err(e) => Box::new(ErroredPlayer(e.to_string())),
             })
             .collect(),
+            full_options.teams,
         )
     }
     pub fn repeat(
         reader: impl std::io::Read + Send + 'static,
         players: Vec<Box<dyn Player<G>>>,
-    ) -> Self {
-        let player_comments = vec![None; players.len()];
-        Self {
+    ) -> std::io::Result<Self> {
+        Self::repeat_with_teams(reader, players, None)
+    }
+    pub fn repeat_with_teams(
+        reader: impl std::io::Read + Send + 'static,
+        players: Vec<Box<dyn Player<G>>>,
+        teams: Option<Vec<usize>>,
+    ) -> std::io::Result<Self> {
+        let teams = teams.unwrap_or_else(|| default_teams(players.len()));
+        check_teams(&teams, players.len())?;
+        let player_results = vec![PlayerResult::default(); players.len()];
+        let debug_commands = Arc::new((0..players.len()).map(|_| AtomicUsize::new(0)).collect());
+        Ok(Self {
             seed: None,
             strategy: Box::new(processor_strategy::Repeat::new(Box::new(
                 std::io::BufReader::new(reader),
             ))),
             players: players.into_iter().map(|player| Some(player)).collect(),
-            player_comments,
+            teams,
+            real_time: None,
+            player_results,
+            debug_commands,
             ticks_processed: 0,
-            tick_handler: None,
-            results_handler: None,
-        }
+            internal_error: None,
+            post_mortem_replay: None,
+            last_good_state: None,
+            observers: Arc::new(Mutex::new(Vec::new())),
+            profiler: ProfilerOptions::from_env().map(profiler::Profiler::new),
+        })
     }
 
-    pub fn set_tick_handler(
-        &mut self,
-        mut handler: Box<dyn FnMut(Option<&Vec<G::Event>>, &G) + Send>,
-    ) {
-        handler(None, self.strategy.game());
-        self.tick_handler = Some(handler);
+    pub fn add_observer(&mut self, mut observer: Box<dyn GameObserver<G>>) {
+        observer.start(self.strategy.game());
+        self.observers.lock().unwrap().push(observer);
+    }
+    pub fn set_tick_handler(&mut self, handler: Box<dyn FnMut(Option<&Vec<G::Event>>, &G) + Send>) {
+        self.add_observer(Box::new(observer::TickHandlerObserver(handler)));
     }
     pub fn set_results_handler(&mut self, handler: Box<dyn FnOnce(FullResults<G>) + Send>) {
-        self.results_handler = Some(handler);
+        self.add_observer(Box::new(observer::ResultsHandlerObserver(Some(handler))));
+    }
+    /// Switch to advancing ticks at a fixed rate, with every player running in its own thread.
+    /// Fails if options do not pass `RealTimeOptions::check`
+    pub fn set_real_time(&mut self, options: RealTimeOptions) -> std::io::Result<()> {
+        options.check()?;
+        self.real_time = Some(real_time::RealTime::new(
+            options,
+            self.players
+                .iter_mut()
+                .map(|player| player.take())
+                .collect(),
+        ));
+        Ok(())
+    }
+    pub fn real_time(&self) -> Option<&RealTimeOptions> {
+        self.real_time.as_ref().map(|real_time| real_time.options())
+    }
+    /// Save replay of the last state before a panic in game logic.
+    /// Game state is cloned every tick for that
+    pub fn set_post_mortem_replay(&mut self, path: std::path::PathBuf) {
+        self.post_mortem_replay = Some(path);
+    }
+    /// Measure time spent in every part of each tick, saving it when processor is dropped.
+    /// Processors are profiled to `CODEGAME_PROFILE` by default, if it is set
+    pub fn set_profiler(&mut self, options: ProfilerOptions) {
+        self.profiler = Some(profiler::Profiler::new(options));
     }
 
     pub(crate) fn debug_update(
@@ -106,17 +191,40 @@ impl<G: Game + 'static> GameProcessor<G> {
         game_state: Option<&G>,
         debug_interface: &DebugInterface<G>,
     ) {
-        for (index, player_cell) in self.players.iter_mut().enumerate() {
-            if let Some(player) = player_cell {
-                if let Err(e) = player.debug_update(
-                    &game_state
-                        .unwrap_or(self.strategy.game())
-                        .player_view(index),
-                    &debug_interface.for_player(index, true),
-                ) {
+        let debug_interface = self.observed_debug_interface(debug_interface);
+        let game = game_state.unwrap_or(self.strategy.game());
+        let players = &self.players;
+        let views = match catch_panic(|| {
+            players
+                .iter()
+                .enumerate()
+                .map(|(index, player)| player.as_ref().map(|_| game.player_view(index)))
+                .collect::<Vec<_>>()
+        }) {
+            Ok(views) => views,
+            Err(message) => {
+                if game_state.is_some() || self.internal_error.is_some() {
+                    error!("Game panicked in player_view: {}", message);
+                } else {
+                    self.handle_internal_error(message);
+                }
+                return;
+            }
+        };
+        // Players are never idle in real-time mode, so they don't get debug updates
+        for (index, (player_cell, view)) in self.players.iter_mut().zip(views).enumerate() {
+            if let (Some(player), Some(view)) = (player_cell.as_mut(), view) {
+                let result = catch_panic(|| {
+                    player.debug_update(&view, &debug_interface.for_player(index, true))
+                })
+                .unwrap_or_else(|message| Err(PlayerError::Panic(message)));
+                self.player_results[index].record_traffic(player.traffic());
+                if let Err(e) = result {
                     *player_cell = None;
-                    warn!("Player error: {}", e);
-                    self.player_comments[index] = Some(format!("Player crashed: {}", e));
+                    self.player_results[index].record_crash(self.ticks_processed, &e);
+                    for observer in self.observers.lock().unwrap().iter_mut() {
+                        observer.player_crash(index, self.ticks_processed, &e);
+                    }
                 }
             }
         }
@@ -127,69 +235,249 @@ impl<G: Game + 'static> GameProcessor<G> {
         debug_interface: Option<&DebugInterface<G>>,
     ) -> Vec<G::Event> {
         assert!(!self.finished());
-        let views: Vec<_> = (0..self.players.len())
-            .map(|index| self.strategy.game().player_view(index))
-            .collect();
-        let action_results = self
-            .players
-            .iter_mut()
-            .zip(views.into_iter())
-            .enumerate()
-            .filter_map(|(index, (player, view))| {
-                player.as_mut().map(|player| {
-                    (
-                        index,
-                        player.get_action(
-                            &view,
-                            debug_interface
-                                .map(|debug_interface| debug_interface.for_player(index, false))
-                                .as_ref(),
-                        ),
-                    )
+        let tick = self.ticks_processed;
+        let game = self.strategy.game();
+        let player_count = self.players.len();
+        let profiler = &mut self.profiler;
+        let views = match catch_panic(|| {
+            (0..player_count)
+                .map(|index| {
+                    if game.player_active(index) {
+                        let start = profiler.as_ref().map(|profiler| profiler.now());
+                        let view = game.player_view(index);
+                        if let (Some(profiler), Some(start)) = (profiler.as_mut(), start) {
+                            profiler.record("player_view", tick, Some(index), start);
+                        }
+                        Some(view)
+                    } else {
+                        None
+                    }
                 })
-            });
-        let player_comments = &mut self.player_comments;
-        let actions: HashMap<usize, G::Action> = action_results
-            .filter_map(|(index, result)| {
-                if let Err(e) = &result {
-                    warn!("Player error: {}", e);
-                    player_comments[index] = Some(format!("Player crashed: {}", e));
+                .collect::<Vec<_>>()
+        }) {
+            Ok(views) => views,
+            Err(message) => {
+                self.handle_internal_error(message);
+                return Vec::new();
+            }
+        };
+        let debug_interface =
+            debug_interface.map(|debug_interface| self.observed_debug_interface(debug_interface));
+        let (actions, errors) = match &mut self.real_time {
+            Some(real_time) => {
+                let start = self.profiler.as_ref().map(|profiler| profiler.now());
+                let result = real_time.get_actions(
+                    tick,
+                    views,
+                    debug_interface.as_ref(),
+                    &mut self.player_results,
+                );
+                let timings = real_time.take_action_timings();
+                if let (Some(profiler), Some(start)) = (&mut self.profiler, start) {
+                    profiler.record("get_actions", tick, None, start);
+                    for timing in timings {
+                        let start = profiler.now() - timing.started.elapsed().as_secs_f64();
+                        profiler.record_span(
+                            "get_action",
+                            timing.tick,
+                            Some(timing.player_index),
+                            start,
+                            timing.duration,
+                        );
+                    }
                 }
-                result.ok().map(|action| (index, action))
-            })
-            .collect();
-        for (index, player) in self.players.iter_mut().enumerate() {
-            if !actions.contains_key(&index) {
-                *player = None;
+                result
+            }
+            None => self.get_actions(views, debug_interface.as_ref()),
+        };
+        for (index, e) in errors {
+            self.player_results[index].record_crash(self.ticks_processed, &e);
+            for observer in self.observers.lock().unwrap().iter_mut() {
+                observer.player_crash(index, self.ticks_processed, &e);
             }
         }
-        let events = self.strategy.process_turn(actions);
-        if let Some(handler) = &mut self.tick_handler {
-            handler(Some(&events), self.strategy.game());
+        if self.post_mortem_replay.is_some() {
+            self.last_good_state = Some(self.strategy.game().clone());
         }
-        if self.finished() {
-            let results = self.strategy.game().results();
-            if let Some(handler) = self.results_handler.take() {
-                handler(FullResults {
-                    players: self
-                        .players
-                        .iter()
-                        .zip(self.player_comments.iter())
-                        .map(|(player, comment)| PlayerResult {
-                            crashed: player.is_none(),
-                            comment: comment.clone(),
-                        })
-                        .collect(),
-                    results,
-                    seed: self.seed,
-                });
+        let observed_actions = if self.observers.lock().unwrap().is_empty() {
+            None
+        } else {
+            Some(actions.clone())
+        };
+        let start = self.profiler.as_ref().map(|profiler| profiler.now());
+        let strategy = &mut self.strategy;
+        let events = match catch_panic(|| strategy.process_turn(actions)) {
+            Ok(events) => events,
+            Err(message) => {
+                self.handle_internal_error(message);
+                return Vec::new();
+            }
+        };
+        if let (Some(profiler), Some(start)) = (&mut self.profiler, start) {
+            profiler.record("process_turn", tick, None, start);
+        }
+        if let Some(actions) = observed_actions {
+            let start = self.profiler.as_ref().map(|profiler| profiler.now());
+            for observer in self.observers.lock().unwrap().iter_mut() {
+                observer.tick(&actions, &events, self.strategy.game());
+            }
+            if let (Some(profiler), Some(start)) = (&mut self.profiler, start) {
+                profiler.record("tick_handlers", tick, None, start);
             }
         }
+        if self.finished() {
+            self.finish();
+        }
         self.ticks_processed += 1;
         trace!("Processed {} ticks", self.ticks_processed);
         events
     }
 
+    /// End the game because of a panic in game logic
+    fn handle_internal_error(&mut self, message: String) {
+        error!(
+            "Game logic panicked on tick {}: {}",
+            self.ticks_processed, message,
+        );
+        self.internal_error = Some(message);
+        if let Some(path) = &self.post_mortem_replay {
+            let game = self
+                .last_good_state
+                .as_ref()
+                .unwrap_or(self.strategy.game());
+            let result = std::fs::File::create(path).and_then(|file| {
+                let mut writer = std::io::BufWriter::new(file);
+                write_replay_start(&mut writer, game)?;
+                writer.flush()
+            });
+            if let Err(e) = result {
+                error!("Failed to save post mortem replay: {}", e);
+            }
+        }
+        self.finish();
+    }
+
+    fn finish(&mut self) {
+        let game = self.strategy.game();
+        let results = match catch_panic(|| game.results()) {
+            Ok(results) => Some(results),
+            Err(message) => {
+                error!("Game panicked while getting results: {}", message);
+                if self.internal_error.is_none() {
+                    self.internal_error = Some(message);
+                }
+                None
+            }
+        };
+        let scores = match results.as_ref().and_then(G::player_scores) {
+            Some(scores) if scores.len() != self.players.len() => {
+                error!(
+                    "Game reported {} scores for {} players",
+                    scores.len(),
+                    self.players.len(),
+                );
+                None
+            }
+            scores => scores,
+        };
+        let mut observers = self.observers.lock().unwrap();
+        if !observers.is_empty() {
+            let players: Vec<PlayerResult> = self
+                .player_results
+                .iter()
+                .zip(self.debug_commands.iter())
+                .map(|(result, debug_commands)| PlayerResult {
+                    debug_commands: debug_commands.load(Ordering::Relaxed),
+                    ..result.clone()
+                })
+                .collect();
+            let team_count = self.teams.iter().max().map_or(0, |&team| team + 1);
+            let teams = (0..team_count)
+                .map(|team| {
+                    let team_players: Vec<usize> = (0..players.len())
+                        .filter(|&index| self.teams[index] == team)
+                        .collect();
+                    TeamResult {
+                        crashed: !team_players.is_empty()
+                            && team_players.iter().all(|&index| players[index].crashed),
+                        score: scores
+                            .as_ref()
+                            .map(|scores| team_players.iter().map(|&index| scores[index]).sum()),
+                        players: team_players,
+                    }
+                })
+                .collect();
+            let full_results = FullResults {
+                players,
+                teams,
+                results,
+                seed: self.seed,
+                internal_error: self.internal_error.clone(),
+            };
+            for observer in observers.iter_mut() {
+                observer.finish(&full_results);
+            }
+        }
+    }
+
+    fn get_actions(
+        &mut self,
+        views: Vec<Option<G::PlayerView>>,
+        debug_interface: Option<&DebugInterface<G>>,
+    ) -> (HashMap<usize, G::Action>, Vec<(usize, PlayerError)>) {
+        let mut actions = HashMap::new();
+        let mut errors = Vec::new();
+        for (index, (player_cell, view)) in self.players.iter_mut().zip(views).enumerate() {
+            if let (Some(player), Some(view)) = (player_cell.as_mut(), view) {
+                let start = self.profiler.as_ref().map(|profiler| profiler.now());
+                let timer = Timer::new();
+                let result = catch_panic(|| {
+                    player.get_action(
+                        &view,
+                        debug_interface
+                            .map(|debug_interface| debug_interface.for_player(index, false))
+                            .as_ref(),
+                    )
+                })
+                .unwrap_or_else(|message| Err(PlayerError::Panic(message)));
+                let response_time = timer.elapsed();
+                if let (Some(profiler), Some(start)) = (&mut self.profiler, start) {
+                    profiler.record("get_action", self.ticks_processed, Some(index), start);
+                }
+                let traffic = player.traffic();
+                match result {
+                    Ok(action) => {
+                        self.player_results[index].record_action(response_time, traffic);
+                        actions.insert(index, action);
+                    }
+                    Err(e) => {
+                        *player_cell = None;
+                        self.player_results[index].record_traffic(traffic);
+                        errors.push((index, e));
+                    }
+                }
+            }
+        }
+        (actions, errors)
+    }
+
+    fn observed_debug_interface(&self, debug_interface: &DebugInterface<G>) -> DebugInterface<G> {
+        let debug_commands = self.debug_commands.clone();
+        let observers = self.observers.clone();
+        let handler = debug_interface.debug_command_handler.clone();
+        DebugInterface {
+            debug_command_handler: Arc::new(move |player_index, global, commands| {
+                debug_commands[player_index].fetch_add(commands.len(), Ordering::Relaxed);
+                for observer in observers.lock().unwrap().iter_mut() {
+                    observer.debug_commands(player_index, global, &commands);
+                }
+                handler(player_index, global, commands);
+            }),
+            debug_state: debug_interface.debug_state.clone(),
+            debug_query: debug_interface.debug_query.clone(),
+        }
+    }
+
     pub fn run(mut self, debug_interface: Option<&DebugInterface<G>>) {
         while !self.finished() {
             self.process_tick(debug_interface);
@@ -204,10 +492,14 @@ impl<G: Game + 'static> GameProcessor<G> {
     }
 
     pub fn finished(&self) -> bool {
-        self.strategy.finished()
+        self.internal_error.is_some() || self.strategy.finished()
     }
 
     pub fn player_count(&self) -> usize {
         self.players.len()
     }
+
+    pub fn teams(&self) -> &[usize] {
+        &self.teams
+    }
 }
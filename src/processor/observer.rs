@@ -0,0 +1,50 @@
+use super::*;
+
+/// Receives notifications about everything happening in a `GameProcessor`
+pub trait GameObserver<G: Game>: Send {
+    /// Called with the initial state when observer is added
+    fn start(&mut self, game: &G) {
+        #![allow(unused_variables)]
+    }
+    /// Events are passed as `Vec` so they can be handed over to tick handlers without copying
+    #[allow(clippy::ptr_arg)]
+    fn tick(&mut self, actions: &HashMap<usize, G::Action>, events: &Vec<G::Event>, game: &G) {
+        #![allow(unused_variables)]
+    }
+    fn player_crash(&mut self, player_index: usize, tick: usize, error: &PlayerError) {
+        #![allow(unused_variables)]
+    }
+    /// Called with every batch of commands sent by a player at once.
+    /// Called from the thread the player runs in, which is not the processor's one in real-time mode
+    fn debug_commands(&mut self, player_index: usize, global: bool, commands: &[DebugCommand<G>]) {
+        #![allow(unused_variables)]
+    }
+    fn finish(&mut self, results: &FullResults<G>) {
+        #![allow(unused_variables)]
+    }
+}
+
+pub(crate) struct TickHandlerObserver<G: Game>(
+    pub Box<dyn FnMut(Option<&Vec<G::Event>>, &G) + Send>,
+);
+
+impl<G: Game> GameObserver<G> for TickHandlerObserver<G> {
+    fn start(&mut self, game: &G) {
+        (self.0)(None, game);
+    }
+    fn tick(&mut self, _actions: &HashMap<usize, G::Action>, events: &Vec<G::Event>, game: &G) {
+        (self.0)(Some(events), game);
+    }
+}
+
+pub(crate) struct ResultsHandlerObserver<G: Game>(
+    pub Option<Box<dyn FnOnce(FullResults<G>) + Send>>,
+);
+
+impl<G: Game> GameObserver<G> for ResultsHandlerObserver<G> {
+    fn finish(&mut self, results: &FullResults<G>) {
+        if let Some(handler) = self.0.take() {
+            handler(results.clone());
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::*;
+
+/// Environment variable with path to write profile to, used when not set in options
+pub const PROFILE_ENV: &str = "CODEGAME_PROFILE";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum ProfileFormat {
+    /// Chrome trace event json, can be opened in `chrome://tracing` or Perfetto
+    ChromeTrace,
+    Csv,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ProfilerOptions {
+    pub path: std::path::PathBuf,
+    /// Guessed from file extension if not specified
+    #[serde(default)]
+    pub format: Option<ProfileFormat>,
+}
+
+impl ProfilerOptions {
+    pub fn from_env() -> Option<Self> {
+        std::env::var_os(PROFILE_ENV).map(|path| Self {
+            path: path.into(),
+            format: None,
+        })
+    }
+    fn format(&self) -> ProfileFormat {
+        self.format.unwrap_or_else(|| {
+            match self
+                .path
+                .extension()
+                .and_then(|extension| extension.to_str())
+            {
+                Some("csv") => ProfileFormat::Csv,
+                _ => ProfileFormat::ChromeTrace,
+            }
+        })
+    }
+}
+
+struct Span {
+    name: &'static str,
+    tick: usize,
+    player_index: Option<usize>,
+    start: f64,
+    duration: f64,
+}
+
+/// Number of profiles saved to every path, so that processors profiled at once
+/// (like when checking determinism) do not overwrite each other's profiles
+static PATH_USES: Mutex<Vec<(std::path::PathBuf, usize)>> = Mutex::new(Vec::new());
+
+/// `path` for the first profile saved to it, `path` with a number before extension for others
+fn unique_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut uses = PATH_USES.lock().unwrap();
+    let index = match uses.iter_mut().find(|(used, _)| used == path) {
+        Some((_, count)) => {
+            *count += 1;
+            *count - 1
+        }
+        None => {
+            uses.push((path.to_owned(), 1));
+            0
+        }
+    };
+    if index == 0 {
+        return path.to_owned();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, index),
+    };
+    let unique = path.with_file_name(file_name);
+    info!(
+        "Profile {} was already saved by another processor, using {}",
+        path.display(),
+        unique.display(),
+    );
+    unique
+}
+
+/// Collects time spent in every part of a tick, writing it to a file when dropped
+/// unless nothing was recorded
+pub(crate) struct Profiler {
+    options: ProfilerOptions,
+    timer: Timer,
+    spans: Vec<Span>,
+}
+
+impl Profiler {
+    pub fn new(options: ProfilerOptions) -> Self {
+        Self {
+            options,
+            timer: Timer::new(),
+            spans: Vec::new(),
+        }
+    }
+    pub fn now(&self) -> f64 {
+        self.timer.elapsed()
+    }
+    /// Record span that started at `start` (as returned by `now`) and ends now
+    pub fn record(
+        &mut self,
+        name: &'static str,
+        tick: usize,
+        player_index: Option<usize>,
+        start: f64,
+    ) {
+        let duration = self.now() - start;
+        self.record_span(name, tick, player_index, start, duration);
+    }
+    /// Record span measured elsewhere, like in a player thread
+    pub fn record_span(
+        &mut self,
+        name: &'static str,
+        tick: usize,
+        player_index: Option<usize>,
+        start: f64,
+        duration: f64,
+    ) {
+        self.spans.push(Span {
+            name,
+            tick,
+            player_index,
+            start,
+            duration,
+        });
+    }
+    fn write_chrome_trace(&self, writer: impl Write) -> std::io::Result<()> {
+        let events: Vec<serde_json::Value> = self
+            .spans
+            .iter()
+            .map(|span| {
+                serde_json::json!({
+                    "name": span.name,
+                    "ph": "X",
+                    "ts": span.start * 1e6,
+                    "dur": span.duration * 1e6,
+                    "pid": 0,
+                    "tid": span.player_index.map_or(0, |index| index + 1),
+                    "args": {
+                        "tick": span.tick,
+                        "player": span.player_index,
+                    },
+                })
+            })
+            .collect();
+        Ok(serde_json::to_writer(
+            writer,
+            &serde_json::json!({ "traceEvents": events }),
+        )?)
+    }
+    fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "tick,name,player,start,duration")?;
+        for span in &self.spans {
+            writeln!(
+                writer,
+                "{},{},{},{:.9},{:.9}",
+                span.tick,
+                span.name,
+                span.player_index
+                    .map_or(String::new(), |index| index.to_string()),
+                span.start,
+                span.duration,
+            )?;
+        }
+        Ok(())
+    }
+    fn save(&self) -> std::io::Result<()> {
+        let mut writer =
+            std::io::BufWriter::new(std::fs::File::create(unique_path(&self.options.path))?);
+        match self.options.format() {
+            ProfileFormat::ChromeTrace => self.write_chrome_trace(&mut writer)?,
+            ProfileFormat::Csv => self.write_csv(&mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        // Nothing was profiled, like when profiler got replaced before running
+        if self.spans.is_empty() {
+            return;
+        }
+        if let Err(e) = self.save() {
+            error!("Failed to save profile: {}", e);
+        }
+    }
+}
@@ -0,0 +1,275 @@
+use super::*;
+
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// What to do for a player that did not answer before the tick deadline
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum MissingActionPolicy {
+    /// Player is not present in actions for this tick
+    Skip,
+    /// Last action received from the player is used again
+    RepeatLast,
+}
+
+impl Default for MissingActionPolicy {
+    fn default() -> Self {
+        Self::Skip
+    }
+}
+
+/// What to do with an answer that arrived after its tick was already processed
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum LateActionPolicy {
+    /// Late answer is ignored
+    Drop,
+    /// Late answer is used as the action for the next tick
+    NextTick,
+}
+
+impl Default for LateActionPolicy {
+    fn default() -> Self {
+        Self::NextTick
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+pub struct RealTimeOptions {
+    pub ticks_per_second: f64,
+    #[serde(default)]
+    pub missing_action: MissingActionPolicy,
+    #[serde(default)]
+    pub late_action: LateActionPolicy,
+}
+
+impl RealTimeOptions {
+    /// Check values that deserialize fine but can not be used
+    pub fn check(&self) -> std::io::Result<()> {
+        if !(self.ticks_per_second.is_finite() && self.ticks_per_second > 0.0) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "real_time.ticks_per_second must be positive, got {}",
+                    self.ticks_per_second,
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct Request<G: Game> {
+    tick: usize,
+    player_view: G::PlayerView,
+    debug_interface: Option<DebugInterface<G>>,
+}
+
+struct Response<G: Game> {
+    tick: usize,
+    result: Result<G::Action, PlayerError>,
+    started: Instant,
+    response_time: f64,
+    traffic: PlayerTraffic,
+}
+
+/// Time a player spent in `get_action`, measured in its own thread
+pub(crate) struct ActionTiming {
+    pub tick: usize,
+    pub player_index: usize,
+    pub started: Instant,
+    pub duration: f64,
+}
+
+struct Worker<G: Game> {
+    requests: mpsc::Sender<Request<G>>,
+    responses: mpsc::Receiver<Response<G>>,
+    busy: bool,
+    last_action: Option<G::Action>,
+}
+
+impl<G: Game> Worker<G> {
+    fn new(player_index: usize, mut player: Box<dyn Player<G>>) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel::<Request<G>>();
+        let (response_sender, response_receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for request in request_receiver {
+                let started = Instant::now();
+                let result = catch_panic(|| {
+                    player.get_action(
+                        &request.player_view,
+                        request
+                            .debug_interface
+                            .as_ref()
+                            .map(|debug_interface| debug_interface.for_player(player_index, false))
+                            .as_ref(),
+                    )
+                })
+                .unwrap_or_else(|message| Err(PlayerError::Panic(message)));
+                let failed = result.is_err();
+                let response = Response {
+                    tick: request.tick,
+                    result,
+                    started,
+                    response_time: started.elapsed().as_secs_f64(),
+                    traffic: player.traffic(),
+                };
+                if response_sender.send(response).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests: request_sender,
+            responses: response_receiver,
+            busy: false,
+            last_action: None,
+        }
+    }
+    fn request(
+        &mut self,
+        tick: usize,
+        player_view: &G::PlayerView,
+        debug_interface: Option<&DebugInterface<G>>,
+    ) -> Result<(), PlayerError> {
+        self.requests
+            .send(Request {
+                tick,
+                player_view: player_view.clone(),
+                debug_interface: debug_interface.cloned(),
+            })
+            .map_err(|_| Self::stopped_error())?;
+        self.busy = true;
+        Ok(())
+    }
+    fn stopped_error() -> PlayerError {
+        PlayerError::IOError(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            "Player thread stopped",
+        ))
+    }
+}
+
+/// Runs every player in its own thread so that ticks advance at a fixed rate
+/// no matter how long players take to answer
+pub(crate) struct RealTime<G: Game> {
+    options: RealTimeOptions,
+    workers: Vec<Option<Worker<G>>>,
+    next_tick_start: Option<Instant>,
+    action_timings: Vec<ActionTiming>,
+}
+
+impl<G: Game> RealTime<G> {
+    pub fn new(options: RealTimeOptions, players: Vec<Option<Box<dyn Player<G>>>>) -> Self {
+        Self {
+            options,
+            workers: players
+                .into_iter()
+                .enumerate()
+                .map(|(index, player)| player.map(|player| Worker::new(index, player)))
+                .collect(),
+            next_tick_start: None,
+            action_timings: Vec::new(),
+        }
+    }
+    pub fn options(&self) -> &RealTimeOptions {
+        &self.options
+    }
+    /// Timings of all answers received since the last call
+    pub fn take_action_timings(&mut self) -> Vec<ActionTiming> {
+        std::mem::take(&mut self.action_timings)
+    }
+    pub fn get_actions(
+        &mut self,
+        tick: usize,
+        views: Vec<Option<G::PlayerView>>,
+        debug_interface: Option<&DebugInterface<G>>,
+        player_results: &mut [PlayerResult],
+    ) -> (HashMap<usize, G::Action>, Vec<(usize, PlayerError)>) {
+        let now = Instant::now();
+        let tick_duration = Duration::from_secs_f64(1.0 / self.options.ticks_per_second);
+        let tick_start = match self.next_tick_start {
+            // Keep the fixed rate unless the processor was idle for a while
+            Some(start) if now < start + tick_duration => start,
+            _ => now,
+        };
+        let deadline = tick_start + tick_duration;
+        let mut actions = HashMap::new();
+        let mut errors = Vec::new();
+        for (index, (worker_cell, view)) in self.workers.iter_mut().zip(views.iter()).enumerate() {
+            if let (Some(worker), Some(view)) = (worker_cell.as_mut(), view) {
+                if !worker.busy {
+                    if let Err(e) = worker.request(tick, view, debug_interface) {
+                        errors.push((index, e));
+                        *worker_cell = None;
+                    }
+                }
+            }
+        }
+        for (index, (worker_cell, view)) in self.workers.iter_mut().zip(views.iter()).enumerate() {
+            let (worker, view) = match (worker_cell.as_mut(), view) {
+                (Some(worker), Some(view)) => (worker, view),
+                _ => continue,
+            };
+            let mut action = None;
+            let mut error = None;
+            while worker.busy {
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let response = match worker.responses.recv_timeout(timeout) {
+                    Ok(response) => response,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        error = Some(Worker::<G>::stopped_error());
+                        break;
+                    }
+                };
+                worker.busy = false;
+                self.action_timings.push(ActionTiming {
+                    tick: response.tick,
+                    player_index: index,
+                    started: response.started,
+                    duration: response.response_time,
+                });
+                player_results[index].record_traffic(response.traffic);
+                match response.result {
+                    Ok(received) => {
+                        player_results[index]
+                            .record_action(response.response_time, response.traffic);
+                        if response.tick == tick
+                            || self.options.late_action == LateActionPolicy::NextTick
+                        {
+                            action = Some(received.clone());
+                        }
+                        worker.last_action = Some(received);
+                    }
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                }
+                if response.tick != tick {
+                    if let Err(e) = worker.request(tick, view, debug_interface) {
+                        error = Some(e);
+                        break;
+                    }
+                }
+            }
+            if let Some(e) = error {
+                errors.push((index, e));
+                *worker_cell = None;
+                continue;
+            }
+            if action.is_none() && self.options.missing_action == MissingActionPolicy::RepeatLast {
+                action = worker.last_action.clone();
+            }
+            if let Some(action) = action {
+                actions.insert(index, action);
+            }
+        }
+        let now = Instant::now();
+        if now < deadline {
+            std::thread::sleep(deadline - now);
+        }
+        self.next_tick_start = Some(deadline);
+        (actions, errors)
+    }
+}
@@ -0,0 +1,196 @@
+use super::*;
+
+use std::hash::Hash;
+
+/// Parameters of the Glicko rating system
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RatingOptions {
+    pub initial_rating: f64,
+    pub initial_deviation: f64,
+    pub min_deviation: f64,
+}
+
+impl Default for RatingOptions {
+    fn default() -> Self {
+        Self {
+            initial_rating: 1500.0,
+            initial_deviation: 350.0,
+            min_deviation: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub games: usize,
+}
+
+impl Rating {
+    /// 95% confidence interval of the rating
+    pub fn confidence_interval(&self) -> (f64, f64) {
+        (
+            self.rating - 1.96 * self.deviation,
+            self.rating + 1.96 * self.deviation,
+        )
+    }
+}
+
+const Q: f64 = std::f64::consts::LN_10 / 400.0;
+
+fn g(deviation: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * Q * Q * deviation * deviation / (std::f64::consts::PI.powi(2))).sqrt()
+}
+
+fn expected_score(rating: &Rating, opponent: &Rating) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-g(opponent.deviation) * (rating.rating - opponent.rating) / 400.0))
+}
+
+/// Ratings of players computed from results of many games.
+///
+/// Every game with several players is treated as a set of pairwise matches,
+/// where player with lower rank wins and equal ranks are a draw.
+pub struct Ratings<G: Game, K> {
+    options: RatingOptions,
+    ranks: Box<dyn Fn(&G::Results) -> Vec<usize>>,
+    ratings: HashMap<K, Rating>,
+}
+
+impl<G: Game, K: Eq + Hash + Clone> Ratings<G, K> {
+    /// `ranks` maps game results to rank of every player, 0 being the best
+    pub fn new(
+        options: RatingOptions,
+        ranks: impl Fn(&G::Results) -> Vec<usize> + 'static,
+    ) -> Self {
+        Self {
+            options,
+            ranks: Box::new(ranks),
+            ratings: HashMap::new(),
+        }
+    }
+    /// Update ratings with results of a single game, `players` identifying every player of that game
+    pub fn add(&mut self, players: &[K], results: &FullResults<G>) -> anyhow::Result<()> {
+        let ranks = (self.ranks)(results.results().context("Game has no results")?);
+        if ranks.len() != players.len() {
+            anyhow::bail!("Got {} ranks for {} players", ranks.len(), players.len());
+        }
+        let initial = Rating {
+            rating: self.options.initial_rating,
+            deviation: self.options.initial_deviation,
+            games: 0,
+        };
+        let before: Vec<Rating> = players
+            .iter()
+            .map(|player| *self.ratings.get(player).unwrap_or(&initial))
+            .collect();
+        for (index, player) in players.iter().enumerate() {
+            let rating = &before[index];
+            let mut delta_sum = 0.0;
+            let mut variance_sum = 0.0;
+            for (opponent_index, opponent) in before.iter().enumerate() {
+                if opponent_index == index {
+                    continue;
+                }
+                let score = match ranks[index].cmp(&ranks[opponent_index]) {
+                    std::cmp::Ordering::Less => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 0.0,
+                };
+                let expected = expected_score(rating, opponent);
+                let weight = g(opponent.deviation);
+                delta_sum += weight * (score - expected);
+                variance_sum += weight * weight * expected * (1.0 - expected);
+            }
+            if variance_sum == 0.0 {
+                continue;
+            }
+            let inv_d2 = Q * Q * variance_sum;
+            let inv_deviation2 = 1.0 / (rating.deviation * rating.deviation) + inv_d2;
+            self.ratings.insert(
+                player.clone(),
+                Rating {
+                    rating: rating.rating + Q / inv_deviation2 * delta_sum,
+                    deviation: partial_max(
+                        (1.0 / inv_deviation2).sqrt(),
+                        self.options.min_deviation,
+                    ),
+                    games: rating.games + 1,
+                },
+            );
+        }
+        Ok(())
+    }
+    /// Add results of many games, stopping at the first invalid one
+    pub fn add_all(
+        &mut self,
+        games: impl IntoIterator<Item = (Vec<K>, FullResults<G>)>,
+    ) -> anyhow::Result<()> {
+        for (index, (players, results)) in games.into_iter().enumerate() {
+            self.add(&players, &results)
+                .with_context(|| format!("Failed to add game {}", index))?;
+        }
+        Ok(())
+    }
+    pub fn get(&self, player: &K) -> Option<&Rating> {
+        self.ratings.get(player)
+    }
+    /// All rated players sorted by rating, best first
+    pub fn leaderboard(&self) -> Leaderboard<K> {
+        let mut entries: Vec<LeaderboardEntry<K>> = self
+            .ratings
+            .iter()
+            .map(|(player, rating)| {
+                let (low, high) = rating.confidence_interval();
+                LeaderboardEntry {
+                    player: player.clone(),
+                    rating: rating.rating,
+                    deviation: rating.deviation,
+                    low,
+                    high,
+                    games: rating.games,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        Leaderboard { entries }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LeaderboardEntry<K> {
+    pub player: K,
+    pub rating: f64,
+    pub deviation: f64,
+    pub low: f64,
+    pub high: f64,
+    pub games: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Leaderboard<K> {
+    pub entries: Vec<LeaderboardEntry<K>>,
+}
+
+impl<K: Serialize + Display> Leaderboard<K> {
+    pub fn write_json(&self, writer: impl Write) -> std::io::Result<()> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+    pub fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "player,rating,deviation,low,high,games")?;
+        for entry in &self.entries {
+            let player = entry.player.to_string();
+            let player = if player.contains(&[',', '"', '\n'][..]) {
+                format!("\"{}\"", player.replace('"', "\"\""))
+            } else {
+                player
+            };
+            writeln!(
+                writer,
+                "{},{:.1},{:.1},{:.1},{:.1},{}",
+                player, entry.rating, entry.deviation, entry.low, entry.high, entry.games,
+            )?;
+        }
+        Ok(())
+    }
+}
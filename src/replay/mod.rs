@@ -0,0 +1,268 @@
+use super::*;
+
+// Replay starts with `REPLAY_MAGIC` and format version,
+// followed by the initial state and events and delta of every tick,
+// each state being followed by its `Game::state_hash`.
+// Replay with debug data has `REPLAY_DEBUG_DATA_MAGIC` and global debug data before the initial state,
+// and debug data of every state after its hash.
+// Since version 2, debug data of every state is followed by messages players sent on it.
+//
+// Replays without `REPLAY_MAGIC` were written before it was added.
+// They have no state hashes, unless they have debug data, which was always written with hashes.
+// Debug data starting with `LEGACY_REPLAY_DEBUG_DATA_MAGIC` was written before layers were added
+
+const REPLAY_MAGIC: &[u8] = b"CGREPLAY";
+const REPLAY_FORMAT_VERSION: u32 = 2;
+const REPLAY_DEBUG_DATA_MAGIC: &[u8] = b"CGDEBUG2";
+const LEGACY_REPLAY_DEBUG_DATA_MAGIC: &[u8] = b"CGDEBUG1";
+
+/// Debug data sent by every player with names of layers, indexed by player
+pub type PlayersDebugData<G> = HashMap<usize, Vec<(String, <G as Game>::DebugData)>>;
+
+/// Debug output of players, other than debug data, saved in replays
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReplayDebugMessage {
+    Log(DebugLogMessage),
+    Breakpoint(DebugBreakpoint),
+}
+
+fn read_debug_data<G: Game>(
+    reader: &mut impl Read,
+    legacy: bool,
+) -> std::io::Result<PlayersDebugData<G>> {
+    let map_err = |e| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+    if !legacy {
+        return bincode::deserialize_from(reader).map_err(map_err);
+    }
+    let data: HashMap<usize, Vec<G::DebugData>> =
+        bincode::deserialize_from(reader).map_err(map_err)?;
+    Ok(data
+        .into_iter()
+        .map(|(player_index, data)| {
+            let data = data
+                .into_iter()
+                .map(|data| (DEFAULT_DEBUG_LAYER.to_owned(), data))
+                .collect();
+            (player_index, data)
+        })
+        .collect())
+}
+
+fn read_debug_messages(reader: &mut impl Read) -> std::io::Result<Vec<ReplayDebugMessage>> {
+    bincode::deserialize_from(reader)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn write_debug_data<G: Game>(
+    writer: &mut dyn Write,
+    debug_data: &PlayersDebugData<G>,
+) -> std::io::Result<()> {
+    bincode::serialize_into(writer, debug_data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+}
+
+/// Write debug data and messages of the state written last
+pub fn write_replay_debug_data<G: Game>(
+    writer: &mut dyn Write,
+    debug_data: &PlayersDebugData<G>,
+    messages: &[ReplayDebugMessage],
+) -> std::io::Result<()> {
+    write_debug_data::<G>(writer, debug_data)?;
+    bincode::serialize_into(writer, messages)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+}
+
+fn write_replay_header(writer: &mut dyn Write) -> std::io::Result<()> {
+    writer.write_all(REPLAY_MAGIC)?;
+    writer.write_all(&REPLAY_FORMAT_VERSION.to_le_bytes())
+}
+
+/// Read replay header if there is one, returning format version
+fn read_replay_header(reader: &mut impl BufRead) -> std::io::Result<Option<u32>> {
+    if !reader.fill_buf()?.starts_with(REPLAY_MAGIC) {
+        return Ok(None);
+    }
+    reader.consume(REPLAY_MAGIC.len());
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version > REPLAY_FORMAT_VERSION {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "Replay format version {} is newer than supported {}",
+                version, REPLAY_FORMAT_VERSION,
+            ),
+        ));
+    }
+    Ok(Some(version))
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Replay state hash mismatch at tick {tick}: expected {expected:016x}, got {actual:016x}")]
+pub struct ReplayDesyncError {
+    /// Number of ticks applied before the mismatch, 0 meaning initial state
+    pub tick: usize,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+pub fn write_replay_start<G: Game>(writer: &mut dyn Write, game: &G) -> std::io::Result<()> {
+    write_replay_header(writer)?;
+    game.write_to(writer)?;
+    game.state_hash().write_to(writer)
+}
+
+/// Start a replay that has debug data, which must then be written after every state
+pub fn write_replay_start_with_debug_data<G: Game>(
+    writer: &mut dyn Write,
+    game: &G,
+    global_debug_data: &PlayersDebugData<G>,
+) -> std::io::Result<()> {
+    write_replay_header(writer)?;
+    writer.write_all(REPLAY_DEBUG_DATA_MAGIC)?;
+    write_debug_data::<G>(writer, global_debug_data)?;
+    game.write_to(writer)?;
+    game.state_hash().write_to(writer)
+}
+
+/// Write a tick that turned `prev` into `current`
+pub fn write_replay_tick<G: Game>(
+    writer: &mut dyn Write,
+    events: &Vec<G::Event>,
+    prev: &G,
+    current: &G,
+) -> std::io::Result<()> {
+    events.write_to(writer)?;
+    prev.diff(current).write_to(writer)?;
+    current.state_hash().write_to(writer)
+}
+
+/// Reads replay tick by tick, verifying state hashes
+pub struct ReplayReader<G: Game, R: BufRead> {
+    reader: R,
+    game: G,
+    tick: usize,
+    has_hashes: bool,
+    warn_on_desync: bool,
+    desync_warned: bool,
+    legacy_debug_data: bool,
+    has_debug_messages: bool,
+    global_debug_data: Option<PlayersDebugData<G>>,
+    debug_data: Option<PlayersDebugData<G>>,
+    debug_messages: Vec<ReplayDebugMessage>,
+}
+
+impl<G: Game, R: BufRead> ReplayReader<G, R> {
+    pub fn new(reader: R) -> std::io::Result<Self> {
+        Self::open(reader, false)
+    }
+    /// Same as `new`, but state hash mismatch is logged as a warning instead of failing,
+    /// so that replays can still be watched
+    pub fn new_with_desync_warnings(reader: R) -> std::io::Result<Self> {
+        Self::open(reader, true)
+    }
+    fn open(mut reader: R, warn_on_desync: bool) -> std::io::Result<Self> {
+        let version = read_replay_header(&mut reader)?;
+        let legacy_debug_data = reader
+            .fill_buf()?
+            .starts_with(LEGACY_REPLAY_DEBUG_DATA_MAGIC);
+        let global_debug_data =
+            if legacy_debug_data || reader.fill_buf()?.starts_with(REPLAY_DEBUG_DATA_MAGIC) {
+                reader.consume(REPLAY_DEBUG_DATA_MAGIC.len());
+                Some(read_debug_data::<G>(&mut reader, legacy_debug_data)?)
+            } else {
+                None
+            };
+        let has_hashes = version.is_some() || global_debug_data.is_some();
+        let game = G::read_from(&mut reader)?;
+        let mut replay = Self {
+            reader,
+            game,
+            tick: 0,
+            has_hashes,
+            warn_on_desync,
+            desync_warned: false,
+            legacy_debug_data,
+            has_debug_messages: version.map_or(false, |version| version >= 2),
+            debug_data: None,
+            debug_messages: Vec::new(),
+            global_debug_data,
+        };
+        if replay.has_hashes {
+            replay.check_hash()?;
+        }
+        if replay.global_debug_data.is_some() {
+            replay.read_state_debug_data()?;
+        }
+        Ok(replay)
+    }
+    fn check_hash(&mut self) -> std::io::Result<()> {
+        let expected = u64::read_from(&mut self.reader)?;
+        let actual = self.game.state_hash();
+        if expected != actual {
+            let error = ReplayDesyncError {
+                tick: self.tick,
+                expected,
+                actual,
+            };
+            if !self.warn_on_desync {
+                return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error));
+            }
+            if !self.desync_warned {
+                warn!("{}, replay may be shown incorrectly", error);
+                self.desync_warned = true;
+            }
+        }
+        Ok(())
+    }
+    fn read_state_debug_data(&mut self) -> std::io::Result<()> {
+        self.debug_data = Some(read_debug_data::<G>(
+            &mut self.reader,
+            self.legacy_debug_data,
+        )?);
+        if self.has_debug_messages {
+            self.debug_messages = read_debug_messages(&mut self.reader)?;
+        }
+        Ok(())
+    }
+    /// Global debug data, if replay has debug data
+    pub fn global_debug_data(&self) -> Option<&PlayersDebugData<G>> {
+        self.global_debug_data.as_ref()
+    }
+    /// Debug data of current state, if replay has debug data
+    pub fn debug_data(&self) -> Option<&PlayersDebugData<G>> {
+        self.debug_data.as_ref()
+    }
+    /// Messages sent by players on current state
+    pub fn debug_messages(&self) -> &[ReplayDebugMessage] {
+        &self.debug_messages
+    }
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+    /// Number of ticks read so far
+    pub fn tick(&self) -> usize {
+        self.tick
+    }
+    pub fn finished(&mut self) -> std::io::Result<bool> {
+        Ok(self.reader.fill_buf()?.is_empty())
+    }
+    /// Advance to next tick, returning its events
+    pub fn next_tick(&mut self) -> std::io::Result<Vec<G::Event>> {
+        let events = Vec::<G::Event>::read_from(&mut self.reader)?;
+        let delta = G::Delta::read_from(&mut self.reader)?;
+        self.game.update(&delta);
+        self.tick += 1;
+        if self.has_hashes {
+            self.check_hash()?;
+        }
+        if self.debug_data.is_some() {
+            self.read_state_debug_data()?;
+        }
+        Ok(events)
+    }
+    pub fn into_game(self) -> G {
+        self.game
+    }
+}
@@ -0,0 +1,180 @@
+use super::*;
+
+use serde_json::Value;
+
+/// Schema with `$schema` and `title` set, ready to be saved as a file.
+/// Subschemas are put into `definitions`
+pub fn json_schema_document<T: JsonSchema>(title: &str) -> Value {
+    let mut schema = schemars::schema_for!(T);
+    schema.schema.metadata().title = Some(title.to_owned());
+    serde_json::to_value(schema).expect("Failed to serialize schema")
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaError {
+    /// Path to the invalid value, like `$.players[1].port`
+    pub path: String,
+    pub reason: String,
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+/// Check value against a schema document, returning all errors found
+pub fn validate_json(schema: &Value, value: &Value) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    validate_at(schema, schema, value, "$", &mut errors);
+    errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => value.is_u64() || value.is_i64(),
+        "number" => value.is_number(),
+        expected => expected == type_name(value),
+    }
+}
+
+fn push_error(errors: &mut Vec<SchemaError>, path: &str, reason: String) {
+    errors.push(SchemaError {
+        path: path.to_owned(),
+        reason,
+    });
+}
+
+/// Resolve `$ref` pointing into the document, like `#/definitions/TcpPlayerOptions`
+fn resolve_ref<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    root.pointer(reference.strip_prefix('#')?)
+}
+
+fn validate_at(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+    errors: &mut Vec<SchemaError>,
+) {
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        match resolve_ref(root, reference) {
+            Some(schema) => validate_at(root, schema, value, path, errors),
+            None => push_error(errors, path, format!("unknown schema {}", reference)),
+        }
+        return;
+    }
+    let expected_types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(expected)) => vec![expected.as_str()],
+        Some(Value::Array(expected)) => expected.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !expected_types.is_empty()
+        && !expected_types
+            .iter()
+            .any(|expected| type_matches(expected, value))
+    {
+        push_error(
+            errors,
+            path,
+            format!(
+                "expected {}, got {}",
+                expected_types.join(" or "),
+                type_name(value),
+            ),
+        );
+        return;
+    }
+    if let Some(Value::Array(variants)) = schema.get("enum") {
+        if !variants.contains(value) {
+            push_error(
+                errors,
+                path,
+                format!("expected one of {}", Value::Array(variants.clone())),
+            );
+        }
+    }
+    if let (Some(minimum), Some(number)) = (
+        schema.get("minimum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if number < minimum {
+            push_error(errors, path, format!("must be at least {}", minimum));
+        }
+    }
+    if let (Some(maximum), Some(number)) = (
+        schema.get("maximum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if number > maximum {
+            push_error(errors, path, format!("must be at most {}", maximum));
+        }
+    }
+    if let (Some(Value::Object(properties)), Value::Object(object)) =
+        (schema.get("properties"), value)
+    {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    push_error(errors, path, format!("missing field `{}`", name));
+                }
+            }
+        }
+        if schema.get("additionalProperties") == Some(&Value::Bool(false)) {
+            for name in object.keys() {
+                if !properties.contains_key(name) {
+                    push_error(errors, path, format!("unknown field `{}`", name));
+                }
+            }
+        }
+        for (name, property_schema) in properties {
+            if let Some(property) = object.get(name) {
+                validate_at(
+                    root,
+                    property_schema,
+                    property,
+                    &format!("{}.{}", path, name),
+                    errors,
+                );
+            }
+        }
+    }
+    if let (Some(items), Value::Array(array)) = (schema.get("items"), value) {
+        for (index, item) in array.iter().enumerate() {
+            validate_at(root, items, item, &format!("{}[{}]", path, index), errors);
+        }
+    }
+    for keyword in &["anyOf", "oneOf"] {
+        if let Some(Value::Array(options)) = schema.get(*keyword) {
+            let mut best: Option<Vec<SchemaError>> = None;
+            for option in options {
+                let mut option_errors = Vec::new();
+                validate_at(root, option, value, path, &mut option_errors);
+                if best
+                    .as_ref()
+                    .map_or(true, |best| option_errors.len() < best.len())
+                {
+                    best = Some(option_errors);
+                }
+            }
+            // Report errors of the closest matching option
+            errors.extend(best.unwrap_or_default());
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            validate_at(root, schema, value, path, errors);
+        }
+    }
+}